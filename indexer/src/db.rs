@@ -0,0 +1,239 @@
+//! SQLite storage of the index.
+//!
+//! Every row keeps the number of the block it came from, so a reorg is handled
+//! by deleting all rows of the retracted blocks. Buckets and promises are stored
+//! as snapshots per block, `buckets` and `promises` views show the latest ones.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction, NO_PARAMS};
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::node::Event;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (
+	number INTEGER PRIMARY KEY,
+	hash TEXT NOT NULL UNIQUE,
+	parent_hash TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+	block_number INTEGER NOT NULL,
+	idx INTEGER NOT NULL,
+	name TEXT NOT NULL,
+	bucket TEXT,
+	promise TEXT,
+	amount TEXT,
+	accounts TEXT NOT NULL,
+	PRIMARY KEY (block_number, idx)
+);
+CREATE TABLE IF NOT EXISTS payments (
+	block_number INTEGER NOT NULL,
+	idx INTEGER NOT NULL,
+	kind TEXT NOT NULL,
+	bucket TEXT NOT NULL,
+	promise TEXT NOT NULL,
+	payee TEXT,
+	amount TEXT NOT NULL,
+	PRIMARY KEY (block_number, idx)
+);
+CREATE TABLE IF NOT EXISTS trades (
+	block_number INTEGER NOT NULL,
+	idx INTEGER NOT NULL,
+	bucket TEXT NOT NULL,
+	buyer TEXT NOT NULL,
+	seller TEXT NOT NULL,
+	price TEXT NOT NULL,
+	PRIMARY KEY (block_number, idx)
+);
+CREATE TABLE IF NOT EXISTS breaches (
+	block_number INTEGER NOT NULL,
+	idx INTEGER NOT NULL,
+	bucket TEXT NOT NULL,
+	promise TEXT NOT NULL,
+	missed TEXT NOT NULL,
+	PRIMARY KEY (block_number, idx)
+);
+CREATE TABLE IF NOT EXISTS bucket_states (
+	id TEXT NOT NULL,
+	block_number INTEGER NOT NULL,
+	owner TEXT,
+	data TEXT,
+	PRIMARY KEY (id, block_number)
+);
+CREATE TABLE IF NOT EXISTS promise_states (
+	id TEXT NOT NULL,
+	block_number INTEGER NOT NULL,
+	issuer TEXT,
+	bucket TEXT,
+	data TEXT,
+	accepted TEXT,
+	PRIMARY KEY (id, block_number)
+);
+CREATE VIEW IF NOT EXISTS buckets AS
+	SELECT s.* FROM bucket_states s
+	WHERE s.block_number = (SELECT MAX(block_number) FROM bucket_states WHERE id = s.id) AND s.data IS NOT NULL;
+CREATE VIEW IF NOT EXISTS promises AS
+	SELECT s.* FROM promise_states s
+	WHERE s.block_number = (SELECT MAX(block_number) FROM promise_states WHERE id = s.id) AND s.data IS NOT NULL;
+";
+
+/// Tables with rows bound to a block.
+const BLOCK_TABLES: &[&str] = &["events", "payments", "trades", "breaches", "bucket_states", "promise_states"];
+
+/// Everything fetched from the node for a block.
+pub struct IndexedBlock {
+	pub number: u64,
+	pub hash: String,
+	pub parent_hash: String,
+	pub events: Vec<Event>,
+	/// Buckets touched by the events: id -> bucket at the block.
+	pub buckets: HashMap<String, Option<Value>>,
+	/// Promises touched by the events: id -> (free promise, accepted promise) at the block.
+	pub promises: HashMap<String, (Option<Value>, Option<Value>)>,
+}
+
+pub struct Db {
+	conn: Connection,
+}
+
+fn amount(value: &Option<Value>) -> Option<String> {
+	value.as_ref().map(|value| match value {
+		Value::String(s) => s.clone(),
+		other => other.to_string(),
+	})
+}
+
+impl Db {
+	pub fn open(path: &str) -> Result<Self> {
+		let conn = Connection::open(path)?;
+		conn.execute_batch(SCHEMA)?;
+		Ok(Db { conn })
+	}
+
+	/// Number of the last indexed block.
+	pub fn last_block(&self) -> Result<Option<u64>> {
+		let number: Option<i64> = self.conn.query_row("SELECT MAX(number) FROM blocks", NO_PARAMS, |row| row.get(0))?;
+		Ok(number.map(|n| n as u64))
+	}
+
+	/// Hash of the indexed block with specified number.
+	pub fn block_hash(&self, number: u64) -> Result<Option<String>> {
+		Ok(self.conn
+			.query_row("SELECT hash FROM blocks WHERE number = ?1", params![number as i64], |row| row.get(0))
+			.optional()?)
+	}
+
+	/// Deletes everything indexed from the block `from` on.
+	pub fn rollback(&mut self, from: u64) -> Result<()> {
+		let tx = self.conn.transaction()?;
+		for table in BLOCK_TABLES {
+			tx.execute(&format!("DELETE FROM {} WHERE block_number >= ?1", table), params![from as i64])?;
+		}
+		tx.execute("DELETE FROM blocks WHERE number >= ?1", params![from as i64])?;
+		tx.commit()?;
+		Ok(())
+	}
+
+	pub fn insert_block(&mut self, block: &IndexedBlock) -> Result<()> {
+		let tx = self.conn.transaction()?;
+		let number = block.number as i64;
+
+		tx.execute(
+			"INSERT INTO blocks (number, hash, parent_hash) VALUES (?1, ?2, ?3)",
+			params![number, block.hash, block.parent_hash],
+		)?;
+
+		let mut owners = HashMap::new();
+		let mut issuers = HashMap::new();
+		let mut accepted_to = HashMap::new();
+
+		for (idx, event) in block.events.iter().enumerate() {
+			let idx = idx as i64;
+			let amount = amount(&event.amount);
+			tx.execute(
+				"INSERT INTO events (block_number, idx, name, bucket, promise, amount, accounts) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+				params![number, idx, event.name, event.bucket, event.promise, amount, serde_json::to_string(&event.accounts)?],
+			)?;
+
+			match (event.name.as_str(), &event.bucket, &event.promise) {
+				("C2fcCreated", Some(bucket), _) | ("Bought", Some(bucket), _) => {
+					owners.insert(bucket.clone(), event.accounts[0].clone());
+				},
+				("Transferred", Some(bucket), _) => {
+					owners.insert(bucket.clone(), event.accounts[1].clone());
+				},
+				("PromiseCreated", _, Some(promise)) => {
+					issuers.insert(promise.clone(), event.accounts[0].clone());
+				},
+				("PromiseAccepted", Some(bucket), Some(promise)) => {
+					accepted_to.insert(promise.clone(), bucket.clone());
+				},
+				_ => {},
+			}
+
+			Self::insert_facts(&tx, number, idx, event, amount)?;
+		}
+
+		for (id, bucket) in block.buckets.iter() {
+			tx.execute(
+				"INSERT INTO bucket_states (id, block_number, owner, data) VALUES (?1, ?2,
+					COALESCE(?3, (SELECT owner FROM bucket_states WHERE id = ?1 ORDER BY block_number DESC LIMIT 1)), ?4)",
+				params![id, number, owners.get(id), bucket.as_ref().map(Value::to_string)],
+			)?;
+		}
+
+		for (id, (free, accepted)) in block.promises.iter() {
+			tx.execute(
+				"INSERT INTO promise_states (id, block_number, issuer, bucket, data, accepted) VALUES (?1, ?2,
+					COALESCE(?3, (SELECT issuer FROM promise_states WHERE id = ?1 ORDER BY block_number DESC LIMIT 1)),
+					COALESCE(?4, (SELECT bucket FROM promise_states WHERE id = ?1 ORDER BY block_number DESC LIMIT 1)),
+					?5, ?6)",
+				params![
+					id, number, issuers.get(id), accepted_to.get(id),
+					free.as_ref().map(Value::to_string), accepted.as_ref().map(Value::to_string),
+				],
+			)?;
+		}
+
+		tx.commit()?;
+		Ok(())
+	}
+
+	/// Payments, trades and breaches derived from the event.
+	fn insert_facts(tx: &Transaction, number: i64, idx: i64, event: &Event, amount: Option<String>) -> Result<()> {
+		let (bucket, promise) = match (&event.bucket, &event.promise) {
+			(Some(bucket), promise) => (bucket, promise),
+			_ => return Ok(()),
+		};
+
+		let payment = |kind: &str, payee: Option<&String>| -> Result<()> {
+			tx.execute(
+				"INSERT INTO payments (block_number, idx, kind, bucket, promise, payee, amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+				params![number, idx, kind, bucket, promise, payee, amount],
+			)?;
+			Ok(())
+		};
+
+		match event.name.as_str() {
+			"PromiseFilled" => payment("fill", None)?,
+			"PromiseAutoPaid" => payment("auto", event.accounts.get(0))?,
+			"StreamClaimed" => payment("stream", event.accounts.get(0))?,
+			"Bought" => {
+				tx.execute(
+					"INSERT INTO trades (block_number, idx, bucket, buyer, seller, price) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+					params![number, idx, bucket, event.accounts[0], event.accounts[1], amount],
+				)?;
+			},
+			"PromiseBreached" => {
+				tx.execute(
+					"INSERT INTO breaches (block_number, idx, bucket, promise, missed) VALUES (?1, ?2, ?3, ?4, ?5)",
+					params![number, idx, bucket, promise, amount],
+				)?;
+			},
+			_ => {},
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,20 @@
+//! Indexer errors.
+
+use derive_more::{Display, From};
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+	/// HTTP transport error.
+	Http(reqwest::Error),
+	/// Database error.
+	Db(rusqlite::Error),
+	/// Malformed JSON.
+	Json(serde_json::Error),
+	/// Error returned by the node.
+	#[display(fmt = "RPC error: {}", _0)]
+	Rpc(String),
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
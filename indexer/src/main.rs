@@ -0,0 +1,75 @@
+//! Akropolis C2FC indexer.
+//!
+//! Replays blocks of a node, decodes `Cashflow` events and storage through
+//! the `c2fc_*` RPC and keeps an SQLite database of buckets, promises,
+//! payments, trades and breaches.
+
+#![warn(missing_docs)]
+
+mod db;
+mod error;
+mod node;
+mod sync;
+
+use std::thread;
+use std::time::Duration;
+
+use log::error;
+use structopt::StructOpt;
+
+/// Command line options.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "akropolis-indexer")]
+struct Opt {
+	/// Substrate JSON-RPC HTTP endpoint of the node.
+	#[structopt(long = "rpc-url", default_value = "http://localhost:9933")]
+	rpc_url: String,
+
+	/// `c2fc_*` JSON-RPC HTTP endpoint of the node.
+	#[structopt(long = "c2fc-rpc-url", default_value = "http://localhost:9955")]
+	c2fc_rpc_url: String,
+
+	/// Path of the SQLite database.
+	#[structopt(long = "db", default_value = "akropolis-index.sqlite")]
+	db: String,
+
+	/// Number of the first block to index.
+	#[structopt(long = "from", default_value = "1")]
+	from: u64,
+
+	/// Index only finalized blocks.
+	#[structopt(long = "finalized")]
+	finalized: bool,
+
+	/// Seconds between polls of the node.
+	#[structopt(long = "interval", default_value = "6")]
+	interval: u64,
+
+	/// Exit after indexing the current head.
+	#[structopt(long = "once")]
+	once: bool,
+}
+
+fn main() {
+	env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+	let opt = Opt::from_args();
+	let node = node::Node::new(opt.rpc_url, opt.c2fc_rpc_url);
+	let mut db = match db::Db::open(&opt.db) {
+		Ok(db) => db,
+		Err(e) => {
+			eprintln!("Unable to open database {}: {}", opt.db, e);
+			std::process::exit(1)
+		},
+	};
+
+	loop {
+		if let Err(e) = sync::sync(&node, &mut db, opt.from, opt.finalized) {
+			error!("Sync failed: {}", e);
+		}
+		if opt.once {
+			break;
+		}
+		thread::sleep(Duration::from_secs(opt.interval));
+	}
+}
@@ -0,0 +1,86 @@
+//! JSON-RPC client of the node.
+
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+/// Header of a block as returned by `chain_getHeader`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+	pub parent_hash: String,
+	/// Hex encoded number.
+	pub number: String,
+}
+
+impl Header {
+	pub fn number(&self) -> Result<u64> {
+		u64::from_str_radix(self.number.trim_start_matches("0x"), 16)
+			.map_err(|e| Error::Rpc(format!("Invalid block number {}: {}", self.number, e)))
+	}
+}
+
+/// Decoded `Cashflow` event as returned by `c2fc_events`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+	pub block_hash: String,
+	pub block_number: u64,
+	pub name: String,
+	pub accounts: Vec<String>,
+	pub bucket: Option<String>,
+	pub promise: Option<String>,
+	pub amount: Option<Value>,
+}
+
+/// Node connection: the default substrate RPC and the `c2fc_*` one.
+pub struct Node {
+	http: reqwest::Client,
+	rpc_url: String,
+	c2fc_url: String,
+}
+
+impl Node {
+	pub fn new(rpc_url: String, c2fc_url: String) -> Self {
+		Node { http: reqwest::Client::new(), rpc_url, c2fc_url }
+	}
+
+	fn call<T: DeserializeOwned>(&self, url: &str, method: &str, params: Value) -> Result<T> {
+		let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+		let mut response: Value = self.http.post(url).json(&request).send()?.json()?;
+		if let Some(error) = response.get("error") {
+			return Err(Error::Rpc(format!("{}: {}", method, error)));
+		}
+		Ok(serde_json::from_value(response["result"].take())?)
+	}
+
+	/// Hash of the block with specified number, `None` if the node doesn't have it.
+	pub fn block_hash(&self, number: u64) -> Result<Option<String>> {
+		self.call(&self.rpc_url, "chain_getBlockHash", json!([number]))
+	}
+
+	pub fn header(&self, hash: Option<&str>) -> Result<Option<Header>> {
+		self.call(&self.rpc_url, "chain_getHeader", json!([hash]))
+	}
+
+	pub fn finalized_head(&self) -> Result<String> {
+		self.call(&self.rpc_url, "chain_getFinalizedHead", json!([]))
+	}
+
+	pub fn events(&self, hash: &str) -> Result<Vec<Event>> {
+		self.call(&self.c2fc_url, "c2fc_events", json!([hash]))
+	}
+
+	pub fn bucket(&self, id: &str, hash: &str) -> Result<Option<Value>> {
+		self.call(&self.c2fc_url, "c2fc_bucket", json!([id, hash]))
+	}
+
+	pub fn promise(&self, id: &str, hash: &str) -> Result<Option<Value>> {
+		self.call(&self.c2fc_url, "c2fc_promise", json!([id, hash]))
+	}
+
+	pub fn accepted_promise(&self, id: &str, hash: &str) -> Result<Option<Value>> {
+		self.call(&self.c2fc_url, "c2fc_acceptedPromise", json!([id, hash]))
+	}
+}
@@ -0,0 +1,88 @@
+//! Replays blocks of the node into the index.
+
+use std::collections::HashMap;
+
+use log::{info, warn};
+
+use crate::db::{Db, IndexedBlock};
+use crate::error::{Error, Result};
+use crate::node::Node;
+
+/// Fetches events and touched buckets and promises of the block.
+fn fetch_block(node: &Node, number: u64, hash: String) -> Result<IndexedBlock> {
+	let header = node.header(Some(&hash))?.ok_or_else(|| Error::Rpc(format!("Unknown block {}", hash)))?;
+	let events = node.events(&hash)?;
+
+	let mut buckets = HashMap::new();
+	let mut promises = HashMap::new();
+	for event in events.iter() {
+		if let Some(id) = &event.bucket {
+			if !buckets.contains_key(id) {
+				buckets.insert(id.clone(), node.bucket(id, &hash)?);
+			}
+		}
+		if let Some(id) = &event.promise {
+			if !promises.contains_key(id) {
+				promises.insert(id.clone(), (node.promise(id, &hash)?, node.accepted_promise(id, &hash)?));
+			}
+		}
+	}
+
+	Ok(IndexedBlock { number, hash, parent_hash: header.parent_hash, events, buckets, promises })
+}
+
+/// Retracts indexed blocks which are not in the canonical chain anymore.
+/// Returns the number of the next block to index.
+fn unwind(node: &Node, db: &mut Db, mut next: u64) -> Result<u64> {
+	while let Some(last) = next.checked_sub(1) {
+		match (db.block_hash(last)?, node.block_hash(last)?) {
+			(None, _) => break,
+			(Some(ours), Some(canonical)) if ours == canonical => break,
+			_ => {
+				warn!("Block #{} is retracted, rolling back", last);
+				db.rollback(last)?;
+				next = last;
+			},
+		}
+	}
+	Ok(next)
+}
+
+/// Indexes all blocks up to the best (or finalized) one.
+/// Returns the number of indexed blocks.
+pub fn sync(node: &Node, db: &mut Db, from: u64, finalized: bool) -> Result<u64> {
+	let head = if finalized {
+		node.header(Some(&node.finalized_head()?))?
+	} else {
+		node.header(None)?
+	};
+	let head = head.ok_or_else(|| Error::Rpc("Node has no head".into()))?.number()?;
+
+	let next = db.last_block()?.map_or(from, |last| last + 1);
+	let mut next = unwind(node, db, next)?;
+	let start = next;
+
+	while next <= head {
+		let hash = match node.block_hash(next)? {
+			Some(hash) => hash,
+			None => break,
+		};
+		let block = fetch_block(node, next, hash)?;
+
+		// the chain was reorganized while syncing, let the next round unwind it
+		if let Some(parent) = db.block_hash(next.saturating_sub(1))? {
+			if next > from && parent != block.parent_hash {
+				warn!("Parent of block #{} is not indexed, chain reorganized", next);
+				break;
+			}
+		}
+
+		db.insert_block(&block)?;
+		if !block.events.is_empty() {
+			info!("Indexed block #{} with {} events", next, block.events.len());
+		}
+		next += 1;
+	}
+
+	Ok(next - start)
+}
@@ -1,26 +1,34 @@
 // use core::convert::AsMut;
 use rstd::result;
+use rstd::prelude::*;
 
 // use primitives::Bytes;
 // use primitives::U256;
 // use primitives::convert_hash;
-use runtime_primitives::traits::{Hash, Zero};
+use runtime_primitives::traits::{As, Bounded, Hash, Saturating, SimpleArithmetic, Zero};
+use runtime_primitives::Permill;
+
+#[cfg(feature = "std")]
+use runtime_io::with_storage;
 
 use support::StorageMap;
 use support::StorageValue;
 use support::dispatch::Result;
 use support::{decl_module, decl_storage, decl_event};
 use support::{ensure, fail};
-use system::ensure_signed;
-use balances::BalanceLock;
+use system::{ensure_signed, ensure_root};
 
-use support::traits::Currency;
-use support::traits::{LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons};
+use support::traits::{Currency, ReservableCurrency, Imbalance, OnUnbalanced};
+use support::traits::{LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons, ExistenceRequirement};
 
 #[cfg(feature = "std")]
 use serde::{Serialize, Deserialize};
 use parity_codec::{Encode, Decode};
 
+use crate::token::{self, AssetId, NATIVE_ASSET};
+use crate::nft::{self, CollectionId, NonFungible};
+use crate::stake::Collateral;
+
 
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
@@ -31,6 +39,13 @@ pub struct Bucket<Hash, Balance, AccountId, BlockNumber> {
 
 	/// price for selling the c2fc
 	price: Balance,
+	/// asset of the price
+	price_asset: AssetId,
+
+	/// account which created the c2fc
+	creator: AccountId,
+	/// part of the price of every resale paid to the creator
+	royalty: Permill,
 }
 
 /// Describes an accepted promise
@@ -46,15 +61,25 @@ pub struct Promise<Hash, Balance, AccountId, BlockNumber> {
 
 	/// promised value to fullfill
 	value: Balance,
+	/// asset the promise is paid in
+	asset: AssetId,
 	/// time (number of blocks)
 	period: BlockNumber,
 	/// time of the end of promise
 	until: Option<BlockNumber>,
+	/// how the payments are distributed over time
+	schedule: Schedule<Balance, BlockNumber>,
+	/// units of `period`, `until` and all other times of the promise
+	clock: Clock,
 
 	/// filled value for current period
 	filled: Balance,
-	/// time (in blocks) when current period was started
+	/// time (in blocks) when the promise was accepted
 	acception_dt: BlockNumber,
+	/// time (in units of `clock`) when the first period was started
+	start_dt: BlockNumber,
+	/// index of the installment which is currently being filled
+	installment: u32,
 }
 
 /// Describes not accepted "free promise"
@@ -64,18 +89,561 @@ pub struct FreePromise<Hash, Balance, /* Stake, */ BlockNumber> {
 	id: Hash,
 	/// promised value to fullfill
 	value: Balance,
+	/// asset the promise is paid in
+	asset: AssetId,
 	/// time (number of blocks)
 	period: BlockNumber,
 	/// time of the end of promise
 	until: Option<BlockNumber>,
+	/// how the payments are distributed over time
+	schedule: Schedule<Balance, BlockNumber>,
+	/// when the first period starts after the acception
+	start: Start<BlockNumber>,
+	/// units of `period`, `until` and all other times of the promise
+	clock: Clock,
+}
+
+/// Units of time used by a promise.
+/// Times of promises with a wall-clock are stored in the `BlockNumber` type
+/// but measured in seconds or months since the unix epoch.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum Clock {
+	/// Number of blocks.
+	Blocks,
+	/// Seconds of `Timestamp::now()`.
+	Seconds,
+	/// Calendar months, a month is due at its first second.
+	Months,
+}
+
+impl Default for Clock {
+	fn default() -> Self { Clock::Blocks }
+}
+
+impl Clock {
+	/// Converts unix time to the clock units. Blocks are not convertible.
+	fn from_seconds(&self, secs: u64) -> Option<u64> {
+		match self {
+			Clock::Blocks => None,
+			Clock::Seconds => Some(secs),
+			Clock::Months => Some(month_index(secs / SECS_PER_DAY)),
+		}
+	}
+
+	/// Converts time in the clock units to unix time. Blocks are not convertible.
+	fn to_seconds(&self, time: u64) -> Option<u64> {
+		match self {
+			Clock::Blocks => None,
+			Clock::Seconds => Some(time),
+			Clock::Months => month_first_day(time).checked_mul(SECS_PER_DAY),
+		}
+	}
+}
+
+/// Start of the first period of a promise.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum Start<BlockNumber> {
+	/// First period starts at the acception.
+	Immediate,
+	/// First period starts the given time after the acception, in units of the promise clock.
+	Delay(BlockNumber),
+	/// First installment is due exactly at the given time, in units of the promise clock.
+	FirstDue(BlockNumber),
+}
+
+impl<BlockNumber> Default for Start<BlockNumber> {
+	fn default() -> Self { Start::Immediate }
+}
+
+/// Payment schedule of a promise.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum Schedule<Balance, BlockNumber> {
+	/// `value` every `period` until `until`.
+	Constant,
+	/// Single payment of `value` at `until`.
+	Bullet,
+	/// `value` for the first period, increased by the step for every next one.
+	StepUp(Balance),
+	/// `value` for the first period, decreased by the step for every next one.
+	StepDown(Balance),
+	/// Explicit list of `(block offset, amount)`, offsets counted from the start of the first period.
+	Installments(Vec<(BlockNumber, Balance)>),
+	/// `value` streamed linearly from the start of the first period till `until`,
+	/// the c2fc owner claims the accrued amount from the escrow at any time.
+	Stream,
+}
+
+impl<Balance, BlockNumber> Default for Schedule<Balance, BlockNumber> {
+	fn default() -> Self { Schedule::Constant }
+}
+
+impl<Balance, BlockNumber> Schedule<Balance, BlockNumber>
+	where Balance: SimpleArithmetic + Copy,
+	      BlockNumber: SimpleArithmetic + Copy,
+{
+	/// Returns the due block and the amount of the `k`-th (zero-based) installment
+	/// of the schedule started at `start`, or `None` if the schedule is over.
+	pub fn installment(&self, value: Balance, period: BlockNumber, until: Option<BlockNumber>, start: BlockNumber, k: u32)
+		-> Option<(BlockNumber, Balance)>
+	{
+		let n = k as u64;
+		let periodic_due = || period.checked_mul(&BlockNumber::sa(n + 1)).and_then(|d| start.checked_add(&d));
+
+		let (due, amount) = match self {
+			Schedule::Constant => (periodic_due()?, value),
+			Schedule::Bullet => return if k == 0 { until.map(|until| (until, value)) } else { None },
+			Schedule::StepUp(step) => {
+				let raise = step.checked_mul(&Balance::sa(n))?;
+				(periodic_due()?, value.checked_add(&raise)?)
+			},
+			Schedule::StepDown(step) => {
+				let cut = step.checked_mul(&Balance::sa(n)).unwrap_or(value);
+				(periodic_due()?, value.saturating_sub(cut))
+			},
+			Schedule::Installments(list) => {
+				let &(offset, amount) = list.get(k as usize)?;
+				(start.checked_add(&offset)?, amount)
+			},
+			Schedule::Stream => return None,
+		};
+
+		match until {
+			Some(until) if due > until => None,
+			_ => Some((due, amount)),
+		}
+	}
+
+	/// Offset of the first installment from the start of the first period.
+	/// `None` if the first due block doesn't depend on the start.
+	pub fn first_offset(&self, period: BlockNumber) -> Option<BlockNumber> {
+		match self {
+			Schedule::Constant | Schedule::StepUp(_) | Schedule::StepDown(_) => Some(period),
+			Schedule::Bullet | Schedule::Stream => None,
+			Schedule::Installments(list) => list.first().map(|&(offset, _)| offset),
+		}
+	}
+
+	/// Checks that the schedule can be applied to the promise terms.
+	/// `max_installments` limits the length of the explicit installments list.
+	pub fn validate(&self, period: BlockNumber, until: Option<BlockNumber>, max_installments: u32) -> result::Result<(), Error> {
+		match self {
+			Schedule::Constant | Schedule::StepUp(_) | Schedule::StepDown(_) => {
+				ensure!(!period.is_zero(), Error::ZeroPeriod);
+			},
+			Schedule::Bullet => {
+				ensure!(until.is_some(), Error::EndRequired);
+			},
+			Schedule::Stream => {
+				ensure!(until.is_some(), Error::EndRequired);
+			},
+			Schedule::Installments(list) => {
+				ensure!(!list.is_empty(), Error::NoInstallments);
+				ensure!(list.len() <= max_installments as usize, Error::TooManyInstallments);
+				let mut last = BlockNumber::zero();
+				for &(offset, _) in list.iter() {
+					ensure!(offset > last, Error::UnorderedInstallments);
+					last = offset;
+				}
+			},
+		}
+		Ok(())
+	}
+}
+
+impl<Hash, Balance, AccountId, BlockNumber> Promise<Hash, Balance, AccountId, BlockNumber>
+	where Balance: SimpleArithmetic + Copy,
+	      BlockNumber: SimpleArithmetic + Copy,
+{
+	/// Returns the installment `k` positions after the current one.
+	pub fn installment_ahead(&self, k: u32) -> Option<(BlockNumber, Balance)> {
+		let index = self.installment.checked_add(k)?;
+		self.schedule.installment(self.value, self.period, self.until, self.start_dt, index)
+	}
+
+	/// Returns the due block and the amount of the installment currently being filled.
+	pub fn current_installment(&self) -> Option<(BlockNumber, Balance)> {
+		self.installment_ahead(0)
+	}
+
+	/// Whether all the installments are closed or the stream is paid out.
+	pub fn is_completed(&self) -> bool {
+		if self.is_stream() {
+			self.filled >= self.value
+		} else {
+			self.current_installment().is_none()
+		}
+	}
+
+	/// Whether the promise is paid by streaming instead of periodic filling.
+	pub fn is_stream(&self) -> bool {
+		match self.schedule {
+			Schedule::Stream => true,
+			_ => false,
+		}
+	}
+
+	/// Total value of the stream accrued by `now`.
+	/// For streams `filled` is the value already paid out to c2fc owners.
+	pub fn accrued(&self, now: BlockNumber) -> Balance {
+		let until = match (self.is_stream(), self.until) {
+			(true, Some(until)) if until > self.start_dt => until,
+			_ => return Zero::zero(),
+		};
+		if now <= self.start_dt {
+			return Zero::zero();
+		}
+		if now >= until {
+			return self.value;
+		}
+
+		let elapsed = Balance::sa((now - self.start_dt).as_());
+		let duration = Balance::sa((until - self.start_dt).as_());
+		match self.value.checked_mul(&elapsed) {
+			Some(value) => value / duration,
+			None => self.value / duration * elapsed,
+		}
+	}
+
+	/// Returns the block when the promise ends, `None` for endless promises.
+	pub fn end_dt(&self) -> Option<BlockNumber> {
+		match (&self.schedule, self.until) {
+			(_, Some(until)) => Some(until),
+			(Schedule::Installments(list), None) => list.last().and_then(|&(offset, _)| self.start_dt.checked_add(&offset)),
+			_ => None,
+		}
+	}
+}
+
+impl<BlockNumber> Start<BlockNumber>
+	where BlockNumber: SimpleArithmetic + Copy,
+{
+	/// Checks that the start can be applied to the schedule.
+	/// Bullet is paid at the end whenever it starts, a stream has no first due time.
+	pub fn validate<Balance>(&self, schedule: &Schedule<Balance, BlockNumber>) -> result::Result<(), Error> {
+		match (self, schedule) {
+			(Start::Delay(_), Schedule::Bullet)
+			| (Start::FirstDue(_), Schedule::Bullet)
+			| (Start::FirstDue(_), Schedule::Stream) => Err(Error::StartNotApplicable),
+			_ => Ok(()),
+		}
+	}
+
+	/// Returns the block when the first period starts for a promise accepted at `now`.
+	pub fn start_dt<Balance>(&self, schedule: &Schedule<Balance, BlockNumber>, period: BlockNumber, now: BlockNumber)
+		-> result::Result<BlockNumber, Error>
+		where Balance: SimpleArithmetic + Copy,
+	{
+		match *self {
+			Start::Immediate => Ok(now),
+			Start::Delay(delay) => now.checked_add(&delay).ok_or(Error::Overflow),
+			Start::FirstDue(first_due) => {
+				ensure!(first_due > now, Error::FirstDuePassed);
+				let offset = schedule.first_offset(period).ok_or(Error::StartNotApplicable)?;
+				first_due.checked_sub(&offset).ok_or(Error::FirstDueTooEarly)
+			},
+		}
+	}
+}
+
+/// Filter of promises listed by `free_promises`.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct PromiseFilter<AccountId, Balance> {
+	/// only promises of the issuer
+	pub issuer: Option<AccountId>,
+	/// only promises with `value` not less than specified
+	pub min_value: Option<Balance>,
+	/// only promises with `value` not greater than specified
+	pub max_value: Option<Balance>,
+	/// only promises paid in the asset
+	pub asset: Option<AssetId>,
+	/// include promises which are already accepted
+	pub with_accepted: bool,
+}
+
+/// Default max number of installments in the explicit schedule, ten years of monthly payments.
+pub const DEFAULT_MAX_INSTALLMENTS: u32 = 120;
+
+/// Default max royalty of the creator, in percents of the price.
+pub const DEFAULT_MAX_ROYALTY: u32 = 10;
+
+/// Parts per million of the rate.
+fn millionths(rate: Permill) -> u64 {
+	rate * 1_000_000u64
+}
+
+/// Market parameters of the module, tunable by the root.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct Parameters<Balance, BlockNumber> {
+	/// min period of a promise, in blocks
+	pub min_period: BlockNumber,
+	/// max value of a single payment, zero for unlimited
+	pub max_value: Balance,
+	/// max number of promises issued by an account, zero for unlimited
+	pub max_promises_per_account: u64,
+	/// fee burned on creation of a c2fc or a promise
+	pub creation_fee: Balance,
+	/// number of blocks after the due time before the installment is breached
+	pub grace_period: BlockNumber,
+	/// deposit reserved for the storage of a c2fc or a promise, returned when it's closed
+	pub storage_deposit: Balance,
+	/// part of the price of a sold c2fc paid to the treasury
+	pub sale_fee: Permill,
+	/// part of the payments filling the promises paid to the treasury
+	pub payment_fee: Permill,
+	/// max number of installments in the explicit schedule of a promise
+	pub max_installments: u32,
+	/// max part of the price paid to the creator of a c2fc, below the whole price
+	pub max_royalty: Permill,
+}
+
+impl<Balance: Zero, BlockNumber: As<u64>> Default for Parameters<Balance, BlockNumber> {
+	fn default() -> Self {
+		Parameters {
+			min_period: BlockNumber::sa(1),
+			max_value: Zero::zero(),
+			max_promises_per_account: 0,
+			creation_fee: Zero::zero(),
+			grace_period: BlockNumber::sa(0),
+			storage_deposit: Zero::zero(),
+			sale_fee: Permill::zero(),
+			payment_fee: Permill::zero(),
+			max_installments: DEFAULT_MAX_INSTALLMENTS,
+			max_royalty: Permill::from_percent(DEFAULT_MAX_ROYALTY),
+		}
+	}
+}
+
+impl<Balance, BlockNumber: Zero> Parameters<Balance, BlockNumber> {
+	pub fn validate(&self) -> result::Result<(), Error> {
+		ensure!(!self.min_period.is_zero(), Error::ZeroPeriod);
+		ensure!(millionths(self.max_royalty) < 1_000_000, Error::RoyaltyTooHigh);
+		Ok(())
+	}
+}
+
+/// Errors of the module.
+/// The index of the error is stable, new errors are added to the end.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+#[repr(u8)]
+pub enum Error {
+	BucketNotFound = 0,
+	PromiseNotFound = 1,
+	NoBucketOwner = 2,
+	NoPromiseOwner = 3,
+	NotBucketOwner = 4,
+	NotPromiseOwner = 5,
+	PromiseAccepted = 6,
+	BucketHasPromise = 7,
+	BucketWithoutPromise = 8,
+	OwnPromise = 9,
+	OwnBucket = 10,
+	NotForSale = 11,
+	PriceAboveMax = 12,
+	PromiseCompleted = 13,
+	ZeroInstallment = 14,
+	InstallmentFilled = 15,
+	StreamNotFillable = 16,
+	NotStream = 17,
+	EndsBeforeStart = 18,
+	ZeroPeriod = 19,
+	EndRequired = 20,
+	NoInstallments = 21,
+	UnorderedInstallments = 22,
+	FirstDuePassed = 23,
+	FirstDueTooEarly = 24,
+	BucketExists = 25,
+	PromiseExists = 26,
+	Overflow = 27,
+	StakeOfAccepted = 28,
+	StakeLocked = 29,
+	LockNotFound = 30,
+	ZeroDeposit = 31,
+	InsufficientEscrow = 32,
+	NothingToClaim = 33,
+	ValueAboveLimit = 34,
+	PeriodBelowLimit = 35,
+	TooManyPromises = 36,
+	InsufficientBalance = 37,
+	TransferFailed = 38,
+	PromiseNotCompleted = 39,
+	PromiseStaked = 40,
+	InsufficientStake = 41,
+	AssetNotFound = 42,
+	NotNativeAsset = 43,
+	NotApproved = 44,
+	TooManyInstallments = 45,
+	StartNotApplicable = 46,
+	NoAssetTreasury = 47,
+	RoyaltyTooHigh = 48,
+	SelfApproval = 49,
+	ArrearsUnpaid = 50,
+	NativeAssetTreasury = 51,
+}
+
+impl Error {
+	/// All the errors in order of their indices.
+	pub const ALL: [Error; 52] = [
+		Error::BucketNotFound, Error::PromiseNotFound, Error::NoBucketOwner, Error::NoPromiseOwner,
+		Error::NotBucketOwner, Error::NotPromiseOwner, Error::PromiseAccepted, Error::BucketHasPromise,
+		Error::BucketWithoutPromise, Error::OwnPromise, Error::OwnBucket, Error::NotForSale,
+		Error::PriceAboveMax, Error::PromiseCompleted, Error::ZeroInstallment, Error::InstallmentFilled,
+		Error::StreamNotFillable, Error::NotStream, Error::EndsBeforeStart, Error::ZeroPeriod,
+		Error::EndRequired, Error::NoInstallments, Error::UnorderedInstallments, Error::FirstDuePassed,
+		Error::FirstDueTooEarly, Error::BucketExists, Error::PromiseExists, Error::Overflow,
+		Error::StakeOfAccepted, Error::StakeLocked, Error::LockNotFound, Error::ZeroDeposit,
+		Error::InsufficientEscrow, Error::NothingToClaim, Error::ValueAboveLimit, Error::PeriodBelowLimit,
+		Error::TooManyPromises, Error::InsufficientBalance, Error::TransferFailed, Error::PromiseNotCompleted,
+		Error::PromiseStaked, Error::InsufficientStake, Error::AssetNotFound, Error::NotNativeAsset,
+		Error::NotApproved, Error::TooManyInstallments, Error::StartNotApplicable, Error::NoAssetTreasury,
+		Error::RoyaltyTooHigh, Error::SelfApproval, Error::ArrearsUnpaid, Error::NativeAssetTreasury,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Error::BucketNotFound => "This c2fc does not exist",
+			Error::PromiseNotFound => "This promise does not exist",
+			Error::NoBucketOwner => "No owner for this c2fc",
+			Error::NoPromiseOwner => "No owner for this promise",
+			Error::NotBucketOwner => "You do not own this c2fc",
+			Error::NotPromiseOwner => "You do not own this promise",
+			Error::PromiseAccepted => "This promise is already accepted",
+			Error::BucketHasPromise => "This c2fc already contains another promise",
+			Error::BucketWithoutPromise => "This c2fc does not contain an accepted promise",
+			Error::OwnPromise => "You can not accept your own promise",
+			Error::OwnBucket => "You can not buy or fill your own c2fc",
+			Error::NotForSale => "This c2fc is not for sale",
+			Error::PriceAboveMax => "This c2fc costs more than your max price",
+			Error::PromiseCompleted => "The promise is already completed",
+			Error::ZeroInstallment => "The current installment of the promise is zero",
+			Error::InstallmentFilled => "The current installment of the promise is already filled",
+			Error::StreamNotFillable => "Streaming promise is paid from the escrow",
+			Error::NotStream => "The promise is not streaming",
+			Error::EndsBeforeStart => "The promise ends before the first payment",
+			Error::ZeroPeriod => "Period can not be zero",
+			Error::EndRequired => "Bullet or streaming promise requires the end",
+			Error::NoInstallments => "Installments list can not be empty",
+			Error::UnorderedInstallments => "Installment offsets should be strictly increasing",
+			Error::FirstDuePassed => "First due time of the promise is already passed",
+			Error::FirstDueTooEarly => "First due time of the promise is before the first period",
+			Error::BucketExists => "This c2fc already exists",
+			Error::PromiseExists => "This promise already exists",
+			Error::Overflow => "Arithmetic overflow",
+			Error::StakeOfAccepted => "The promise is accepted so the stake can not be withdrawn",
+			Error::StakeLocked => "The stake is still locked",
+			Error::LockNotFound => "The stake lock is not found",
+			Error::ZeroDeposit => "Deposit can not be zero",
+			Error::InsufficientEscrow => "Not enough funds in the escrow",
+			Error::NothingToClaim => "Nothing to claim",
+			Error::ValueAboveLimit => "Promise value exceeds the limit",
+			Error::PeriodBelowLimit => "Promise period is shorter than the limit",
+			Error::TooManyPromises => "Too many promises of the account",
+			Error::InsufficientBalance => "Not enough free balance",
+			Error::TransferFailed => "Transfer failed",
+			Error::PromiseNotCompleted => "The promise is not completed yet",
+			Error::PromiseStaked => "The promise is staked, withdraw the stake first",
+			Error::InsufficientStake => "Not enough AKT to stake",
+			Error::AssetNotFound => "This asset does not exist",
+			Error::NotNativeAsset => "Escrow and streaming are available only for the native asset",
+			Error::NotApproved => "You are not the owner of this c2fc nor approved by the owner",
+			Error::TooManyInstallments => "Installments list is longer than the limit",
+			Error::StartNotApplicable => "Bullet promise starts immediately, streaming promise has no first due time",
+			Error::NoAssetTreasury => "No treasury account receives the fees in this asset",
+			Error::RoyaltyTooHigh => "Royalty of the c2fc exceeds the limit",
+			Error::SelfApproval => "You can not approve yourself as an operator",
+			Error::ArrearsUnpaid => "The escrow still owes the arrears of the stream to the previous owners",
+			Error::NativeAssetTreasury => "Native fees go to the treasury module",
+		}
+	}
+}
+
+impl From<Error> for &'static str {
+	fn from(e: Error) -> &'static str {
+		e.as_str()
+	}
 }
 
+/// Max number of installments summed up by `due_amount`.
+const MAX_DUE_LOOKAHEAD: u32 = 1024;
 
-pub trait Trait: system::Trait + balances::Trait {
+/// Number of promises in the page of `free_promises`.
+pub const PROMISES_PAGE_SIZE: u32 = 50;
+
+/// Version of the storage layout, the older storage is migrated at the first block.
+/// 1. assets, schedules, clocks and royalties of buckets and promises.
+/// 2. owners of buckets in the NFT registry.
+pub const STORAGE_VERSION: u32 = 2;
+
+
+pub trait Trait: system::Trait + balances::Trait + timestamp::Trait + token::Trait + nft::Trait {
+	/// Currency locked as collateral of promises, payments stay in the native balance.
 	type Stake: LockableCurrency<Self::AccountId, Moment = <Self as system::Trait>::BlockNumber>;
+	/// Rewards the stake of the promises paid on time.
+	type OnPeriodPaid: OnPeriodPaid<Self::AccountId, Self::Hash, StakeBalanceOf<Self>>;
+	/// Delegations backing the promises of the issuers.
+	type Backing: Backing<Self::AccountId, Self::Hash, StakeBalanceOf<Self>>;
+	/// Receives the protocol fees of the market.
+	type ProtocolFee: OnUnbalanced<NegativeImbalanceOf<Self>>;
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
+type StakeBalanceOf<T> = <<T as Trait>::Stake as Currency<<T as system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> = <balances::Module<T> as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
+
+/// Collection of the c2fcs in the `nft` registry.
+pub struct BucketTokens;
+
+impl nft::Collected for BucketTokens {
+	const COLLECTION: CollectionId = *b"c2fcbckt";
+}
+
+/// Ownership of the c2fcs.
+type BucketRegistry<T> = nft::Collection<T, BucketTokens>;
+
+/// Hook called when a period of the staked promise is closed as paid on time.
+pub trait OnPeriodPaid<AccountId, Hash, Balance> {
+	/// The period of the promise staked by `issuer` with `own` AKT and `delegated` held AKT is paid.
+	fn on_period_paid(promise_id: Hash, issuer: &AccountId, own: Balance, delegated: Balance);
+}
+
+impl<AccountId, Hash, Balance> OnPeriodPaid<AccountId, Hash, Balance> for () {
+	fn on_period_paid(_: Hash, _: &AccountId, _: Balance, _: Balance) {}
+}
+
+/// AKT delegated to the issuers of the promises.
+pub trait Backing<AccountId, Hash, Balance> {
+	/// AKT delegated to the issuer and not held by its stakes, they add to the collateral capacity of the issuer.
+	fn delegated(issuer: &AccountId) -> Balance;
+	/// AKT bonded by the account, they can't back its stakes.
+	fn bonded(who: &AccountId) -> Balance;
+	/// Hold `value` of the AKT delegated to the issuer as a part of the promise stake.
+	fn hold(issuer: &AccountId, value: Balance);
+	/// Release `value` of the held AKT when the stake is withdrawn.
+	fn release(issuer: &AccountId, value: Balance);
+	/// The promise staked by `issuer` with `own` AKT and `delegated` held AKT is breached.
+	/// Returns the slashed (own, delegated) AKT.
+	fn on_breach(promise_id: Hash, issuer: &AccountId, own: Balance, delegated: Balance) -> (Balance, Balance);
+}
+
+impl<AccountId, Hash, Balance: Zero> Backing<AccountId, Hash, Balance> for () {
+	fn delegated(_: &AccountId) -> Balance {
+		Zero::zero()
+	}
+	fn bonded(_: &AccountId) -> Balance {
+		Zero::zero()
+	}
+	fn hold(_: &AccountId, _: Balance) {}
+	fn release(_: &AccountId, _: Balance) {}
+	fn on_breach(_: Hash, _: &AccountId, _: Balance, _: Balance) -> (Balance, Balance) {
+		(Zero::zero(), Zero::zero())
+	}
+}
+
 
 decl_event!(
 	pub enum Event<T>
@@ -83,6 +651,7 @@ decl_event!(
 		<T as system::Trait>::AccountId,
 		<T as system::Trait>::Hash,
 		<T as balances::Trait>::Balance,
+		StakeBalance = StakeBalanceOf<T>,
 	{
 		C2fcCreated(AccountId, Hash),
 		/// OwnerSet: from, to, c2fc
@@ -106,9 +675,55 @@ decl_event!(
 		PromiseBreached(Hash, Hash, Balance),
 
 		// Staking / Locking:
-		Stake(Hash, AccountId, Balance),
-		// Stake(Hash, AccountId, StakeBalance<Self>),
-		Withdraw(Hash, AccountId, Balance),
+		/// (promise_id:Hash, issuer:AccountId, amount:StakeBalance)
+		Stake(Hash, AccountId, StakeBalance),
+		/// (promise_id:Hash, issuer:AccountId, amount:StakeBalance)
+		Withdraw(Hash, AccountId, StakeBalance),
+
+		// Escrow / Auto-pay:
+		/// (promise_id:Hash, issuer:AccountId, amount:Balance)
+		EscrowDeposited(Hash, AccountId, Balance),
+		/// (promise_id:Hash, issuer:AccountId, amount:Balance)
+		EscrowWithdrawn(Hash, AccountId, Balance),
+		/// Installment is paid from the escrow to the c2fc owner.
+		/// (c2fc_id:Hash, promise_id:Hash, owner:AccountId, value:Balance)
+		PromiseAutoPaid(Hash, Hash, AccountId, Balance),
+		/// Accrued value of the stream is paid from the escrow.
+		/// (c2fc_id:Hash, promise_id:Hash, owner:AccountId, value:Balance)
+		StreamClaimed(Hash, Hash, AccountId, Balance),
+
+		/// Market parameters are changed by the root.
+		ParametersChanged,
+
+		/// c2fc is deleted by the owner.
+		/// (owner:AccountId, c2fc_id:Hash)
+		C2fcClosed(AccountId, Hash),
+		/// Promise is deleted by the issuer or with the c2fc.
+		/// (issuer:AccountId, promise_id:Hash)
+		PromiseClosed(AccountId, Hash),
+
+		/// Call of the account failed with the error.
+		/// (who:AccountId, error:Error)
+		Failed(AccountId, Error),
+
+		/// Protocol fee of the sale or the payment is paid to the treasury.
+		/// (c2fc_id:Hash, payer:AccountId, fee:Balance)
+		FeePaid(Hash, AccountId, Balance),
+		/// Royalty of the resale is paid to the creator of the c2fc.
+		/// (c2fc_id:Hash, creator:AccountId, royalty:Balance)
+		RoyaltyPaid(Hash, AccountId, Balance),
+		/// Account is approved to transfer the c2fc, the owner means no approval.
+		/// (owner:AccountId, approved:AccountId, c2fc_id:Hash)
+		Approval(AccountId, AccountId, Hash),
+		/// Operator is approved or disapproved to transfer all the c2fcs of the owner.
+		/// (owner:AccountId, operator:AccountId, approved:bool)
+		ApprovalForAll(AccountId, AccountId, bool),
+		/// Account receiving the protocol fees in the asset is set by the root.
+		/// (asset:AssetId, treasury:AccountId)
+		AssetTreasurySet(AssetId, AccountId),
+		/// Stake locked in the native balance before the upgrade is unlocked, the issuer can stake AKT again.
+		/// (promise_id:Hash, issuer:AccountId, amount:Balance)
+		LegacyStakeUnlocked(Hash, AccountId, Balance),
 	}
 );
 
@@ -116,17 +731,12 @@ decl_event!(
 decl_storage! {
 	trait Store for Module<T: Trait> as Cashflow {
 		Buckets get(c2fc): map T::Hash => Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>;
-		BucketOwner get(owner_of_c2fc): map T::Hash => Option<T::AccountId>;
 		/// same as `AcceptedPromiseBucket` but by c2fc_id
 		BucketContributor get(contributor_of_c2fc): map T::Hash => Option<T::AccountId>;
-
-		AllBucketsArray get(c2fc_by_index): map u64 => T::Hash;
-		AllBucketsCount get(all_c2fc_count): u64;
-		AllBucketsIndex: map T::Hash => u64;
-
-		OwnedBucketsArray get(c2fc_of_owner_by_index): map (T::AccountId, u64) => T::Hash;
-		OwnedBucketsCount get(owned_c2fc_count): map T::AccountId => u64;
-		OwnedBucketsIndex: map T::Hash => u64;
+		/// c2fc_id -> account approved to transfer the c2fc, cleared on transfer
+		BucketApproval get(approved_of_c2fc): map T::Hash => Option<T::AccountId>;
+		/// (owner, operator) -> whether the operator can transfer and approve all the c2fcs of the owner
+		OperatorApproval get(is_approved_for_all): map (T::AccountId, T::AccountId) => bool;
 
 
 		// free promises:
@@ -154,184 +764,342 @@ decl_storage! {
 		LocksCount get(locks_count): u64;
 		/// promise_id -> LockIdentifier
 		LockForPromise get(lock_for_promise): map T::Hash => LockIdentifier;
+		/// promise_id -> (amount, until) of the stake locked by the issuer
+		Staked get(staked): map T::Hash => (StakeBalanceOf<T>, T::BlockNumber);
+		/// promise_id -> part of the stake held from the AKT delegated to the issuer
+		StakedDelegated get(staked_delegated): map T::Hash => StakeBalanceOf<T>;
+		/// issuer -> own AKT staked to all its promises
+		IssuerStaked get(issuer_staked): map T::AccountId => StakeBalanceOf<T>;
+
+		/// c2fc_id or promise_id -> (depositor, amount) reserved for the storage
+		StorageDeposit get(storage_deposit): map T::Hash => Option<(T::AccountId, T::Balance)>;
+
+		/// market parameters
+		Params get(parameters) config(parameters): Parameters<T::Balance, T::BlockNumber>;
+		/// asset -> account receiving the protocol fees in the asset, native fees go to `ProtocolFee`
+		AssetTreasury get(asset_treasury): map AssetId => Option<T::AccountId>;
+
+		/// promise_id -> balance reserved by the issuer to pay the promise automatically
+		Escrow get(escrow_of): map T::Hash => T::Balance;
+		/// (promise_id, account) -> accrued value of the stream which the escrow couldn't pay
+		StreamArrears get(stream_arrears): map (T::Hash, T::AccountId) => T::Balance;
+		/// promise_id -> arrears of the stream owed to all the accounts
+		TotalArrears get(total_arrears): map T::Hash => T::Balance;
 
 		Nonce: u64;
+
+		/// version of the storage layout, see `STORAGE_VERSION`
+		StorageVersion get(storage_version) build(|_: &GenesisConfig<T>| STORAGE_VERSION): u32;
+	}
+	add_extra_genesis {
+		// (owner, c2fc_id, price)
+		config(buckets): Vec<(T::AccountId, T::Hash, T::Balance)>;
+		// (issuer, promise_id, value, period, until, schedule, start, clock), zero `until` means endless promise
+		config(promises): Vec<(T::AccountId, T::Hash, T::Balance, T::BlockNumber, T::BlockNumber, Schedule<T::Balance, T::BlockNumber>, Start<T::BlockNumber>, Clock)>;
+		// (promise_id, c2fc_id), accepted by the owner of the c2fc
+		config(accepted): Vec<(T::Hash, T::Hash)>;
+		// (promise_id, amount), staked by the issuer of the promise
+		config(stakes): Vec<(T::Hash, StakeBalanceOf<T>)>;
+
+		build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+			with_storage(storage, || {
+				// storage is written by the internal helpers, so the genesis emits no events
+				let check = |what: &str, id: &T::Hash, res: result::Result<(), Error>| if let Err(e) = res {
+					panic!("Invalid c2fc genesis {} {:?}: {}", what, id, e.as_str());
+				};
+
+				for (owner, c2fc_id, price) in config.buckets.iter().cloned() {
+					let c2fc = Bucket { id: c2fc_id, promise: None, price, price_asset: NATIVE_ASSET, creator: owner.clone(), royalty: Permill::zero() };
+					check("bucket", &c2fc_id, <Module<T>>::mint_c2fc(owner.clone(), c2fc_id, c2fc)
+						.and_then(|_| <Module<T>>::reserve_deposit(&owner, c2fc_id)));
+				}
+
+				for (issuer, promise_id, value, period, until, schedule, start, clock) in config.promises.iter().cloned() {
+					check("promise", &promise_id, <Module<T>>::do_create_promise(issuer.clone(), promise_id, value, NATIVE_ASSET, period, until, schedule, start, clock)
+						.and_then(|_| <Module<T>>::reserve_deposit(&issuer, promise_id)));
+				}
+
+				for &(promise_id, c2fc_id) in &config.accepted {
+					check("acception of the promise", &promise_id, <Module<T>>::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)
+						.and_then(|owner| <Module<T>>::do_accept_promise(&owner, promise_id, c2fc_id)));
+				}
+
+				// the AKT genesis is built after C2FC, it checks the own AKT of the stakes:
+				for &(promise_id, amount) in &config.stakes {
+					check("stake of the promise", &promise_id, <Module<T>>::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)
+						.and_then(|issuer| <Module<T>>::lock_stake(&issuer, promise_id, amount, Zero::zero())));
+				}
+			});
+		});
+	}
+}
+
+
+/// Storage of the runtime before `STORAGE_VERSION` 1, it's read and cleared only by the migration.
+mod legacy {
+	use super::Trait;
+	use support::decl_storage;
+	use parity_codec::{Encode, Decode};
+
+	#[derive(Encode, Decode, Default)]
+	pub struct Bucket<Hash, Balance, AccountId, BlockNumber> {
+		pub id: Hash,
+		pub promise: Option<Promise<Hash, Balance, AccountId, BlockNumber>>,
+		pub price: Balance,
+	}
+
+	#[derive(Encode, Decode, Default)]
+	pub struct Promise<Hash, Balance, AccountId, BlockNumber> {
+		pub id: Hash,
+		pub owner: AccountId,
+		pub value: Balance,
+		pub period: BlockNumber,
+		pub until: Option<BlockNumber>,
+		pub filled: Balance,
+		pub acception_dt: BlockNumber,
+	}
+
+	#[derive(Encode, Decode, Default)]
+	pub struct FreePromise<Hash, Balance, BlockNumber> {
+		pub id: Hash,
+		pub value: Balance,
+		pub period: BlockNumber,
+		pub until: Option<BlockNumber>,
+	}
+
+	pub struct Module<T: Trait>(rstd::marker::PhantomData<T>);
+
+	decl_storage! {
+		trait Store for Module<T: Trait> as Cashflow {
+			pub Buckets: map T::Hash => Option<Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>>;
+			pub Promises: map T::Hash => Option<FreePromise<T::Hash, T::Balance, T::BlockNumber>>;
+			pub BucketOwner: map T::Hash => Option<T::AccountId>;
+
+			pub AllBucketsArray: map u64 => T::Hash;
+			pub AllBucketsCount: u64;
+			pub AllBucketsIndex: map T::Hash => u64;
+
+			pub OwnedBucketsArray: map (T::AccountId, u64) => T::Hash;
+			pub OwnedBucketsCount: map T::AccountId => u64;
+			pub OwnedBucketsIndex: map T::Hash => u64;
+		}
 	}
 }
 
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		fn deposit_event<T>() = default;
+		/// Max number of installments summed up by `due_amount`.
+		const MaxDueLookahead: u32 = MAX_DUE_LOOKAHEAD;
+		/// Number of promises in the page of `free_promises`.
+		const PromisesPageSize: u32 = PROMISES_PAGE_SIZE;
 
-		fn create_c2fc(origin) -> Result {
-			let sender = ensure_signed(origin)?;
-			let nonce = <Nonce<T>>::get();
-			let c2fc_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
+		fn deposit_event<T>() = default;
 
-			let new_c2fc = Bucket {
-					id: c2fc_id,
-					promise: None,
-					price: T::Balance::zero(),
-			};
+		/// Set the market parameters. Only root can do it.
+		fn set_parameters(origin, parameters: Parameters<T::Balance, T::BlockNumber>) -> Result {
+			ensure_root(origin)?;
+			parameters.validate()?;
 
-			Self::mint_c2fc(sender, c2fc_id, new_c2fc)?;
+			<Params<T>>::put(parameters);
 
-			<Nonce<T>>::mutate(|n| *n += 1);
+			Self::deposit_event(RawEvent::ParametersChanged);
 
 			Ok(())
 		}
 
-		fn create_promise_until(origin, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber) -> Result {
-			let sender = ensure_signed(origin)?;
-			let nonce = <Nonce<T>>::get();
-			let promise_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
-
-			let new_promise = FreePromise {
-				id: promise_id,
-				value,
-				period,
-				until: if !until.is_zero() { Some(until) } else { None },
-			};
+		/// Set the account receiving the protocol fees in the issued asset. Only root can do it.
+		fn set_asset_treasury(origin, asset: AssetId, treasury: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(asset != NATIVE_ASSET, Error::NativeAssetTreasury.as_str());
+			ensure!(<token::Module<T>>::asset_exists(asset), Error::AssetNotFound.as_str());
 
-			Self::mint_promise(sender, promise_id, new_promise)?;
+			<AssetTreasury<T>>::insert(asset, treasury.clone());
 
-			<Nonce<T>>::mutate(|n| *n += 1);
+			Self::deposit_event(RawEvent::AssetTreasurySet(asset, treasury));
 
 			Ok(())
 		}
 
-		fn create_promise(origin, value: T::Balance, period: T::BlockNumber) -> Result {
-			Self::create_promise_until(origin, value, period, Zero::zero())
+		fn create_c2fc(origin) -> Result {
+			Self::create_c2fc_with_royalty(origin, Permill::zero())
 		}
 
-
-		// TODO: fn stake_to_promise(origin, promise_id: T::Hash, amount: StakeBalance<T>) -> Result {
-		fn stake_to_promise(origin, promise_id: T::Hash, amount: T::Balance) -> Result {
+		/// Create c2fc which pays the `royalty` of the price to the sender when it's resold by other owners.
+		fn create_c2fc_with_royalty(origin, royalty: Permill) -> Result {
 			let sender = ensure_signed(origin)?;
-
-			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
-			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
-			ensure!(owner == sender, "You do not own this promise");
-
-			// get data from existing promise:
-			let until = if <AcceptedPromiseBucket<T>>::exists(promise_id) {
-				let promise = {
-					let c2fc_id = <AcceptedPromiseBucket<T>>::get(promise_id);
-					let c2fc = Self::c2fc(c2fc_id);
-					let promise = c2fc.promise;
-					ensure!(promise.is_some(), "Bucket doesnt contains promise");
-					promise.unwrap()
+			Self::report(sender.clone(), || {
+				ensure!(millionths(royalty) <= millionths(Self::parameters().max_royalty), Error::RoyaltyTooHigh);
+				Self::ensure_can_deposit(&sender)?;
+				let nonce = <Nonce<T>>::get();
+				let c2fc_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
+
+				let new_c2fc = Bucket {
+						id: c2fc_id,
+						promise: None,
+						price: T::Balance::zero(),
+						price_asset: NATIVE_ASSET,
+						creator: sender.clone(),
+						royalty,
 				};
-				promise.until
-			} else {
-				let promise = Self::promise(promise_id);
-				promise.until
-			}.unwrap_or( unsafe {
-				// end of the universe:
-				// TODO: use (crate::)BlockNumber::max_value()
-				// <T as system::Trait>::BlockNumber::from(crate::BlockNumber::max_value())
-				// <T::BlockNumber as As<crate::BlockNumber>>::sa(max as crate::BlockNumber);
-				// XXX:
-				let max = crate::BlockNumber::max_value();
-				(*(max as *const crate::BlockNumber as *const <T as system::Trait>::BlockNumber)).clone()
-			});
 
+				Self::mint_c2fc(sender.clone(), c2fc_id, new_c2fc)?;
+				Self::charge_creation_fee(&sender)?;
+				Self::reserve_deposit(&sender, c2fc_id)?;
 
-			let reasons = WithdrawReasons::from(WithdrawReason::Reserve);
+				<Nonce<T>>::mutate(|n| *n += 1);
 
-			if <LockForPromise<T>>::exists(promise_id) {
-				let lock_id = Self::lock_for_promise(promise_id);
-				// select lock with specified ID:
-				let lock = get_lock::<T>(&sender, &lock_id);
-				let lock = { // XXX: test & remove me
-					let locks_all = <balances::Module<T>>::locks(&sender);
-					let mut locks = locks_all.into_iter().filter_map(|l|
-						if l.id == lock_id {
-							Some(l)
-						} else {
-							None
-						});
-					let lock = locks.next();
-					ensure!(lock.is_none(), "Lock not found");
-					ensure!(locks.next().is_some(), "Incorrect length of locks with same ID. WTF?!");
-					lock.unwrap()
-				};
+				Self::deposit_event(RawEvent::C2fcCreated(sender, c2fc_id));
 
-				// TODO: check overflow:
-				// ensure!(T::Balance::max_value() - lock.amount >= amount, "Overflow max size of Balance!");
-				// e.g. crate::BlockNumber::max_value() - <T::Balance as As<crate::Balance>>::sa(lock.amount as crate::Balance) >= <T::Balance as As<crate::Balance>>::sa(amount as crate::Balance)
+				Ok(())
+			})
+		}
 
-				<balances::Module<T>>::extend_lock(lock_id, &sender, lock.amount + amount, until, reasons);
-				// <T::Stake>::extend_lock(lock_id, &sender, lock.amount + amount, until, reasons);
-			} else {
-				let lock_id = Self::next_free_lock_identifier(&promise_id);
+		/// Create free promise with the specified payment schedule and start of the first period.
+		/// All the times are measured in units of `clock`.
+		/// Zero `until` means the promise never ends.
+		fn create_scheduled_promise(origin, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber, schedule: Schedule<T::Balance, T::BlockNumber>, start: Start<T::BlockNumber>, clock: Clock) -> Result {
+			Self::create_asset_promise(origin, NATIVE_ASSET, value, period, until, schedule, start, clock)
+		}
 
-				// <T::Stake>::set_lock(lock_id, &sender, amount, until, reasons);
-				<balances::Module<T>>::set_lock(lock_id, &sender, amount, until, reasons);
+		/// Create free promise paid in the specified asset, see `create_scheduled_promise`.
+		/// Streaming promises are paid from the escrow, so they can be only in the native asset.
+		fn create_asset_promise(origin, asset: AssetId, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber, schedule: Schedule<T::Balance, T::BlockNumber>, start: Start<T::BlockNumber>, clock: Clock) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				let nonce = <Nonce<T>>::get();
+				let promise_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
 
-				// TODO: use T::Stake instead T::Balance:
-				// <T::Stake>::set_lock(lock_id, &sender, amount, until, reasons);
-				// <balances::Module<T::Stake>>::set_lock(lock_id, &sender, amount, until, reasons);
+				Self::ensure_can_deposit(&sender)?;
+				Self::do_create_promise(sender.clone(), promise_id, value, asset, period, until, schedule, start, clock)?;
+				Self::charge_creation_fee(&sender)?;
+				Self::reserve_deposit(&sender, promise_id)?;
 
-				// register new lock:
-				<LockForPromise<T>>::insert(promise_id, lock_id);
-				<LocksCount<T>>::mutate(|n| *n += 1);
-			}
+				<Nonce<T>>::mutate(|n| *n += 1);
 
-			Self::deposit_event(RawEvent::Stake(promise_id, sender, amount));
+				Self::deposit_event(RawEvent::PromiseCreated(sender, promise_id));
 
-			Ok(())
+				Ok(())
+			})
 		}
 
-		fn withdraw_staken(origin, promise_id: T::Hash) -> Result {
+		fn create_promise_until(origin, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber) -> Result {
+			Self::create_scheduled_promise(origin, value, period, until, Schedule::Constant, Start::Immediate, Clock::Blocks)
+		}
+
+		fn create_promise(origin, value: T::Balance, period: T::BlockNumber) -> Result {
+			Self::create_promise_until(origin, value, period, Zero::zero())
+		}
+
+
+		/// Lock `amount` of AKT of the issuer as collateral of the promise.
+		fn stake_to_promise(origin, promise_id: T::Hash, amount: StakeBalanceOf<T>) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				Self::do_stake(&sender, promise_id, amount)?;
 
-			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+				Self::deposit_event(RawEvent::Stake(promise_id, sender, amount));
 
-			let owner = Self::owner_of_c2fc(promise_id).ok_or("No owner for this promise")?;
-			ensure!(owner == sender, "You do not own this promise");
+				Ok(())
+			})
+		}
 
-			if <LockForPromise<T>>::exists(promise_id) {
-				let lock_id = Self::lock_for_promise(promise_id);
+		fn withdraw_staken(origin, promise_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
 
-				let lock = get_lock::<T>(&sender, &lock_id);
+				let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				ensure!(owner == sender, Error::NotPromiseOwner);
 
-				if let Some(lock) = &lock {
+				if <LockForPromise<T>>::exists(promise_id) {
+					let (_, until) = Self::staked(promise_id);
 					let now = <system::Module<T>>::block_number();
-					ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise already accepted so stake cannot withdraw.");
-					ensure!(lock.until <= now, "This locked balance period isn't ended and stake cannot withdraw.");
+					ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), Error::StakeOfAccepted);
+					ensure!(until <= now, Error::StakeLocked);
+
+					let staked = Self::unstake(&sender, promise_id);
+
+					Self::deposit_event(RawEvent::Withdraw(promise_id, sender, staked));
 				}
 
-				let free = {
-					lock.map(|lock| lock.amount)
-				}.unwrap_or(Zero::zero());
+				Ok(())
+			})
+		}
 
-				<balances::Module<T>>::remove_lock(lock_id, &sender);
 
-				Self::deposit_event(RawEvent::Withdraw(promise_id, sender, free));
-			}
+		/// Reserve funds of the issuer to pay the promise automatically at every due time.
+		fn deposit_escrow(origin, promise_id: T::Hash, amount: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
+				let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				ensure!(owner == sender, Error::NotPromiseOwner);
+				ensure!(!amount.is_zero(), Error::ZeroDeposit);
+				ensure!(Self::promise(promise_id).asset == NATIVE_ASSET, Error::NotNativeAsset);
+
+				let escrow = Self::escrow_of(promise_id).checked_add(&amount).ok_or(Error::Overflow)?;
+				<balances::Module<T> as ReservableCurrency<T::AccountId>>::reserve(&sender, amount)
+					.map_err(|_| Error::InsufficientBalance)?;
+				<Escrow<T>>::insert(promise_id, escrow);
+
+				Self::deposit_event(RawEvent::EscrowDeposited(promise_id, sender, amount));
+
+				Ok(())
+			})
+		}
 
-			Ok(())
+		/// Return unspent funds of the escrow to the issuer.
+		fn withdraw_escrow(origin, promise_id: T::Hash, amount: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				ensure!(owner == sender, Error::NotPromiseOwner);
+
+				let escrow = Self::escrow_of(promise_id);
+				// value of the stream accrued to the c2fc owner and the arrears owed to the previous owners
+				// stay in the escrow until they're claimed
+				let owed = Self::accepted_promise(promise_id)
+					.filter(|promise| promise.is_stream())
+					.map(|promise| promise.accrued(Self::now(&promise.clock)).saturating_sub(promise.filled))
+					.unwrap_or_else(Zero::zero)
+					.saturating_add(Self::total_arrears(promise_id));
+				ensure!(amount <= escrow.saturating_sub(owed), Error::InsufficientEscrow);
+
+				// `unreserve` returns the part which is not reserved anymore, e.g. slashed
+				let left = <balances::Module<T> as ReservableCurrency<T::AccountId>>::unreserve(&sender, amount);
+				let withdrawn = amount - left;
+				<Escrow<T>>::insert(promise_id, escrow - withdrawn);
+
+				Self::deposit_event(RawEvent::EscrowWithdrawn(promise_id, sender, withdrawn));
+
+				Ok(())
+			})
 		}
 
 
 		fn edit_promise(origin, promise_id: T::Hash, value: T::Balance, period: T::BlockNumber) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
 
-			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+				let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				ensure!(owner == sender, Error::NotPromiseOwner);
 
-			let owner = Self::owner_of_c2fc(promise_id).ok_or("No owner for this promise")?;
-			ensure!(owner == sender, "You do not own this promise");
+				ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), Error::PromiseAccepted);
+				let free_promise = Self::promise(promise_id);
+				free_promise.schedule.validate(period, free_promise.until, Self::parameters().max_installments)?;
+				Self::check_limits(value, period, &free_promise.schedule, &free_promise.clock)?;
 
-			<Promises<T>>::mutate(promise_id, |promise|{
-				promise.value = value;
-				promise.period = period;
-			});
+				<Promises<T>>::mutate(promise_id, |promise|{
+					promise.value = value;
+					promise.period = period;
+				});
 
-			Self::deposit_event(RawEvent::PromiseChanged(promise_id));
+				Self::deposit_event(RawEvent::PromiseChanged(promise_id));
 
-			Ok(())
+				Ok(())
+			})
 		}
 
 
@@ -339,116 +1107,182 @@ decl_module! {
 		/// Only owner of the c2fc can do it.
 		fn accept_promise(origin, promise_id: T::Hash, c2fc_id: T::Hash) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				Self::do_accept_promise(&sender, promise_id, c2fc_id)?;
 
-			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
-			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
-			ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise is already accepted");
+				<Nonce<T>>::mutate(|n| *n += 1);
 
+				Self::deposit_event(RawEvent::PromiseAccepted(promise_id, c2fc_id));
 
-			let c2fc_owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(c2fc_owner == sender, "You do not own this promise");
+				Ok(())
+			})
+		}
 
-			let promise_owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
-			ensure!(promise_owner != sender, "You can not accept your own promise");
 
-			let mut c2fc = Self::c2fc(c2fc_id);
-			ensure!(c2fc.promise.is_none(), "Bucket already contains another promise");
+		/// Delete the c2fc with its completed promise and return the storage deposits.
+		/// Only owner of the c2fc can do it.
+		fn close_c2fc(origin, c2fc_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
 
-			// get current (latest) block:
-			let current_block = <system::Module<T>>::block_number();
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner == sender, Error::NotBucketOwner);
 
-			let free_promise = Self::promise(promise_id);
-			let promise = Promise {
-				id: free_promise.id,
-				// in the near future `owner` can be removed
-				owner: promise_owner.clone(),
-				value: free_promise.value,
-				period: free_promise.period,
-				until: free_promise.until,
-				acception_dt: current_block,
-				filled: T::Balance::zero(),
-			};
+				let c2fc = Self::c2fc(c2fc_id);
+				if let Some(promise) = &c2fc.promise {
+					ensure!(promise.is_completed(), Error::PromiseNotCompleted);
+					// the escrow is returned to the issuer on close, so the arrears it can pay are claimed first
+					ensure!(Self::total_arrears(promise.id).is_zero() || Self::escrow_of(promise.id).is_zero(), Error::ArrearsUnpaid);
+				}
 
-			c2fc.promise = Some(promise);
-			<Buckets<T>>::insert(c2fc_id, c2fc);
-			<AcceptedPromiseBucket<T>>::insert(promise_id, c2fc_id);
+				if let Some(promise) = c2fc.promise {
+					Self::remove_promise(promise.owner, promise.id);
+				}
+				Self::remove_c2fc(sender, c2fc_id);
 
-			// incrmnt the counter & push to maps:
-			{
-				let accepted_promises_count = Self::accepted_promises_count();
-				let new_accepted_promises_count = accepted_promises_count
-					.checked_add(1)
-					.ok_or("Overflow adding a new promise to total supply")?;
+				Ok(())
+			})
+		}
 
-				<BucketContributor<T>>::insert(c2fc_id, promise_owner);
+		/// Delete the free promise and return the storage deposit and the escrow.
+		/// Only issuer of the promise can do it.
+		fn close_promise(origin, promise_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
 
-				<AcceptedPromisesArray<T>>::insert(accepted_promises_count, promise_id);
-				<AcceptedPromisesCount<T>>::put(new_accepted_promises_count);
-				<AcceptedPromisesIndex<T>>::insert(promise_id, accepted_promises_count);
-			}
+				let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				ensure!(owner == sender, Error::NotPromiseOwner);
 
-			<Nonce<T>>::mutate(|n| *n += 1);
+				ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), Error::PromiseAccepted);
+				ensure!(!<LockForPromise<T>>::exists(promise_id), Error::PromiseStaked);
 
-			Self::deposit_event(RawEvent::PromiseAccepted(promise_id, c2fc_id));
+				Self::remove_promise(sender, promise_id);
 
-			Ok(())
+				Ok(())
+			})
 		}
 
 
 		// selling & trasfering a c2fc //
 
 		fn set_price(origin, c2fc_id: T::Hash, new_price: T::Balance) -> Result {
-			let sender = ensure_signed(origin)?;
+			Self::set_asset_price(origin, c2fc_id, NATIVE_ASSET, new_price)
+		}
 
-			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
+		/// Set the price of the c2fc in the specified asset.
+		/// Only owner of the c2fc can do it.
+		fn set_asset_price(origin, c2fc_id: T::Hash, asset: AssetId, new_price: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
 
-			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(owner == sender, "You do not own this c2fc");
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner == sender, Error::NotBucketOwner);
+				ensure!(<token::Module<T>>::asset_exists(asset), Error::AssetNotFound);
 
-			let mut c2fc = Self::c2fc(c2fc_id);
-			c2fc.price = new_price;
+				let mut c2fc = Self::c2fc(c2fc_id);
+				c2fc.price = new_price;
+				c2fc.price_asset = asset;
 
-			<Buckets<T>>::insert(c2fc_id, c2fc);
+				<Buckets<T>>::insert(c2fc_id, c2fc);
 
-			Self::deposit_event(RawEvent::PriceSet(sender, c2fc_id, new_price));
+				Self::deposit_event(RawEvent::PriceSet(sender, c2fc_id, new_price));
 
-			Ok(())
+				Ok(())
+			})
 		}
 
 		fn transfer(origin, to: T::AccountId, c2fc_id: T::Hash) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner == sender, Error::NotBucketOwner);
 
-			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(owner == sender, "You do not own this c2fc");
+				Self::do_transfer(sender, to, c2fc_id)?;
 
-			Self::transfer_from(sender, to, c2fc_id)?;
+				Ok(())
+			})
+		}
 
-			Ok(())
+		/// Approve the `operator` to transfer the c2fc, approving the owner clears the approval.
+		/// Can be called by the owner or by an operator approved for all the c2fcs of the owner.
+		fn approve(origin, c2fc_id: T::Hash, operator: T::AccountId) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner == sender || Self::is_approved_for_all((owner.clone(), sender)), Error::NotApproved);
+
+				if operator == owner {
+					<BucketApproval<T>>::remove(c2fc_id);
+				} else {
+					<BucketApproval<T>>::insert(c2fc_id, &operator);
+				}
+
+				Self::deposit_event(RawEvent::Approval(owner, operator, c2fc_id));
+
+				Ok(())
+			})
 		}
 
-		fn buy_c2fc(origin, c2fc_id: T::Hash, max_price: T::Balance) -> Result {
+		/// Approve or disapprove the `operator` to transfer and approve all the c2fcs of the sender.
+		fn set_approval_for_all(origin, operator: T::AccountId, approved: bool) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(operator != sender, Error::SelfApproval);
+
+				if approved {
+					<OperatorApproval<T>>::insert((sender.clone(), operator.clone()), true);
+				} else {
+					<OperatorApproval<T>>::remove((sender.clone(), operator.clone()));
+				}
+
+				Self::deposit_event(RawEvent::ApprovalForAll(sender, operator, approved));
 
-			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
+				Ok(())
+			})
+		}
 
-			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(owner != sender, "You can't buy your own c2fc");
+		/// Transfer the c2fc of `from`, by the owner or by an approved account.
+		fn transfer_from(origin, from: T::AccountId, to: T::AccountId, c2fc_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(Self::is_approved_or_owner(&sender, c2fc_id), Error::NotApproved);
 
-			let mut c2fc = Self::c2fc(c2fc_id);
+				Self::do_transfer(from, to, c2fc_id)?;
 
-			let c2fc_price = c2fc.price;
-			ensure!(!c2fc_price.is_zero(), "The c2fc you want to buy is not for sale");
-			ensure!(c2fc_price <= max_price, "The c2fc you want to buy costs more than your max price");
+				Ok(())
+			})
+		}
 
-			Self::transfer_money(&sender, &owner, c2fc_price)?;
-			Self::transfer_from(owner.clone(), sender.clone(), c2fc_id)?;
+		fn buy_c2fc(origin, c2fc_id: T::Hash, max_price: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
 
-			c2fc.price = T::Balance::zero();
-			<Buckets<T>>::insert(c2fc_id, c2fc);
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner != sender, Error::OwnBucket);
 
-			Self::deposit_event(RawEvent::Bought(sender, owner, c2fc_id, c2fc_price));
+				let c2fc = Self::c2fc(c2fc_id);
+				let c2fc_price = c2fc.price;
+				ensure!(!c2fc_price.is_zero(), Error::NotForSale);
+				ensure!(c2fc_price <= max_price, Error::PriceAboveMax);
+
+				ensure!(<token::Module<T>>::free_balance_of(c2fc.price_asset, &sender) >= c2fc_price, Error::InsufficientBalance);
+				ensure!(BucketRegistry::<T>::balance_of(&sender).checked_add(1).is_some(), Error::Overflow);
+				// the royalty is paid with the price, so nothing is paid if any part fails
+				let royalty = Self::royalty_of(&c2fc, &owner, c2fc_price);
+				Self::pay_with_fee(c2fc_id, c2fc.price_asset, &sender, &owner, c2fc_price, Self::parameters().sale_fee, (&c2fc.creator, royalty))?;
+				if !royalty.is_zero() {
+					Self::deposit_event(RawEvent::RoyaltyPaid(c2fc_id, c2fc.creator.clone(), royalty));
+				}
+				Self::do_transfer(owner.clone(), sender.clone(), c2fc_id)?;
 
-			Ok(())
+				Self::deposit_event(RawEvent::Bought(sender, owner, c2fc_id, c2fc_price));
+
+				Ok(())
+			})
 		}
 
 
@@ -456,85 +1290,173 @@ decl_module! {
 
 		fn fill_c2fc(origin, c2fc_id: T::Hash, deposit: T::Balance) -> Result {
 			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
 
-			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
-
-			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(owner != sender, "You can't fill your own c2fc");
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner != sender, Error::OwnBucket);
 
-			let mut c2fc = Self::c2fc(c2fc_id);
-			ensure!(c2fc.promise.is_some(), "This c2fc does not contains promise");
+				let mut c2fc = Self::c2fc(c2fc_id);
+				ensure!(c2fc.promise.is_some(), Error::BucketWithoutPromise);
 
 
-			if let Some(ref mut promise) = c2fc.promise {
-				let promise_id = promise.id;
+				if let Some(ref mut promise) = c2fc.promise {
+					let promise_id = promise.id;
 
-				ensure!(!promise.value.is_zero(), "The promise in the c2fc you want to fill is invalid");
-				ensure!(promise.filled <= promise.value, "The c2fc you want to fill is already fullfilled");
+					ensure!(!promise.is_stream(), Error::StreamNotFillable);
+					ensure!(!deposit.is_zero(), Error::ZeroDeposit);
+					let (_, due) = promise.current_installment().ok_or(Error::PromiseCompleted)?;
+					ensure!(!due.is_zero(), Error::ZeroInstallment);
+					ensure!(promise.filled < due, Error::InstallmentFilled);
+					let filled = promise.filled.checked_add(&deposit).ok_or(Error::Overflow)?;
 
-				Self::transfer_money(&sender, &owner, deposit)?;
+					Self::pay_with_fee(c2fc_id, promise.asset, &sender, &owner, deposit, Self::parameters().payment_fee, (&owner, Zero::zero()))?;
 
-				promise.filled = deposit + promise.filled;
+					promise.filled = filled;
 
-				Self::deposit_event(RawEvent::PromiseFilled(c2fc_id, promise_id, deposit));
+					Self::deposit_event(RawEvent::PromiseFilled(c2fc_id, promise_id, deposit));
 
-				if promise.filled >= promise.value {
-					Self::deposit_event(RawEvent::PromiseFullilled(c2fc_id, promise_id));
+					if promise.filled >= due {
+						Self::deposit_event(RawEvent::PromiseFullilled(c2fc_id, promise_id));
+					}
 				}
-			}
 
-			// re-store the c2fc
-			<Buckets<T>>::insert(c2fc_id, c2fc);
+				// re-store the c2fc
+				<Buckets<T>>::insert(c2fc_id, c2fc);
 
-			Ok(())
+				Ok(())
+			})
 		}
 
 		fn fullfill_c2fc(origin, c2fc_id: T::Hash) -> Result {
-			let deposit = {
-				ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
+			let sender = ensure_signed(origin)?;
+			let deposit = Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
 				let c2fc = Self::c2fc(c2fc_id);
-				let promise = &c2fc.promise.ok_or("This c2fc doesnt contains an accepted promise")?;
-				let deposit = promise.filled - promise.value;
-				deposit
-			};
+				let promise = &c2fc.promise.ok_or(Error::BucketWithoutPromise)?;
+				let (_, due) = promise.current_installment().ok_or(Error::PromiseCompleted)?;
+				ensure!(promise.filled < due, Error::InstallmentFilled);
+				Ok(due - promise.filled)
+			})?;
+
+			Self::fill_c2fc(system::RawOrigin::Signed(sender).into(), c2fc_id, deposit)
+		}
+
+		/// Claim the value of the streaming promise accrued by now.
+		/// Only owner of the c2fc can do it.
+		fn claim_stream(origin, c2fc_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
+
+				let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+				ensure!(owner == sender, Error::NotBucketOwner);
+
+				let c2fc = Self::c2fc(c2fc_id);
+				let promise = c2fc.promise.ok_or(Error::BucketWithoutPromise)?;
+				ensure!(promise.is_stream(), Error::NotStream);
+
+				Self::checkpoint_stream(c2fc_id, &sender);
 
-			Self::fill_c2fc(origin, c2fc_id, deposit)
+				Ok(())
+			})
+		}
+
+		/// Claim the accrued value of the stream which the escrow couldn't pay before.
+		fn claim_arrears(origin, promise_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			Self::report(sender.clone(), || {
+				let arrears = Self::stream_arrears((promise_id, sender.clone()));
+				ensure!(!arrears.is_zero(), Error::NothingToClaim);
+
+				let issuer = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+				let c2fc_id = Self::c2fc_by_promise(promise_id);
+				let paid = Self::pay_from_escrow(c2fc_id, promise_id, &issuer, &sender, arrears);
+				ensure!(!paid.is_zero(), Error::InsufficientEscrow);
+
+				<StreamArrears<T>>::insert((promise_id, sender.clone()), arrears - paid);
+				<TotalArrears<T>>::mutate(promise_id, |v| *v = v.saturating_sub(paid));
+
+				Self::deposit_event(RawEvent::StreamClaimed(c2fc_id, promise_id, sender, paid));
+
+				Ok(())
+			})
 		}
 
 
 
+		/// Migrate the storage of the older runtime.
+		fn on_initialize(_n: T::BlockNumber) {
+			if Self::storage_version() < STORAGE_VERSION {
+				Self::migrate();
+			}
+		}
+
 		/// Check the breach of promise at end of the each block.
 		/// Simple timer here.
 		fn on_finalize(n: T::BlockNumber) {
 			let accepted_promises_count = Self::accepted_promises_count();
+			// installments are breached only after the grace period:
+			let deadline = n.saturating_sub(Self::parameters().grace_period);
 
 			for i in 0..accepted_promises_count {
 				let promise_id = Self::accepted_promise_by_index(i);
 				let c2fc_id = Self::c2fc_by_promise(promise_id);
 
 				if <Buckets<T>>::exists(c2fc_id) {
-					let c2fc = Self::c2fc(c2fc_id);
+					let mut c2fc = Self::c2fc(c2fc_id);
+					let mut changed = false;
+
 					// skip if c2fc doesn't contains a promise
-					if let Some(promise) = &c2fc.promise {
-						let lifetime = n - promise.acception_dt;
-						// fix attempt to subtract with overflow:
-						// let wanted_deposit = (promise.filled.into() as i128) - (promise.value.into() as i128);
-						let wanted_deposit = if promise.filled > promise.value {
-							Some(promise.value - promise.value)
-						} else { None };
-
-						// if (lifetime % promise.period).is_zero() && !wanted_deposit.is_zero() {
-						if let (Some(wanted_deposit), true) = (wanted_deposit, (lifetime % promise.period).is_zero()) {
-							// TODO: reset `promise.filled` to zero because new period starts.
-
-							if wanted_deposit > <T::Balance>::zero() {
+					if let Some(ref mut promise) = c2fc.promise {
+						let now = Self::time_at(&promise.clock, n);
+						let breach_dt = Self::time_at(&promise.clock, deadline);
+
+						// close every installment that is due by now:
+						while let Some((due_dt, due)) = promise.current_installment() {
+							if due_dt > now {
+								break;
+							}
+
+							if promise.filled < due {
+								if let Some(owner) = Self::owner_of_c2fc(c2fc_id) {
+									let paid = Self::pay_from_escrow(c2fc_id, promise_id, &promise.owner, &owner, due - promise.filled);
+									if !paid.is_zero() {
+										promise.filled = promise.filled + paid;
+										changed = true;
+										Self::deposit_event(RawEvent::PromiseAutoPaid(c2fc_id, promise_id, owner, paid));
+									}
+								}
+							}
+
+							if promise.filled >= due {
+								// overpayment goes to the next installment
+								promise.filled = promise.filled - due;
+
+								// nothing is paid for the empty installments, so they are not rewarded:
+								if !due.is_zero() {
+									if let Some((own, delegated)) = Self::stake_of(promise_id) {
+										T::OnPeriodPaid::on_period_paid(promise_id, &promise.owner, own, delegated);
+									}
+								}
+							} else if due_dt <= breach_dt {
 								// here we should to emit Event about *failed promise*.
-								Self::deposit_event(RawEvent::PromiseBreached(c2fc_id, promise_id, wanted_deposit));
-								// TODO: slash the stake
-								// <BucketContributor<T>>::...(c2fc_id,);
+								Self::deposit_event(RawEvent::PromiseBreached(c2fc_id, promise_id, due - promise.filled));
+								Self::breach_stake(promise_id, &promise.owner);
+								promise.filled = Zero::zero();
+							} else {
+								// still in the grace period
+								break;
 							}
+
+							promise.installment += 1;
+							changed = true;
 						}
 					}
+
+					if changed {
+						<Buckets<T>>::insert(c2fc_id, c2fc);
+					}
 				}
 			}
 		}
@@ -544,20 +1466,157 @@ decl_module! {
 
 // private & utils //
 
-fn get_lock<T: Trait>(
-	who: &T::AccountId,
-	lock_id: &LockIdentifier,
-) -> Option<BalanceLock<T::Balance, T::BlockNumber>> {
-	let locks_all = <balances::Module<T>>::locks(who);
-	let mut locks = locks_all
-		.into_iter()
-		.filter_map(|l| if &l.id == lock_id { Some(l) } else { None });
-	locks.next()
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Number of calendar months since 1970-01 for the day since the unix epoch.
+fn month_index(days: u64) -> u64 {
+	// civil from days, see http://howardhinnant.github.io/date_algorithms.html
+	let z = days + 719_468;
+	let era = z / 146_097;
+	let doe = z - era * 146_097;
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year - 1970) * 12 + (month - 1)
+}
+
+/// Day since the unix epoch of the first day of the month with specified `month_index`.
+fn month_first_day(month_index: u64) -> u64 {
+	// days from civil, see http://howardhinnant.github.io/date_algorithms.html
+	let month = month_index % 12 + 1;
+	let year = 1970 + month_index / 12 - if month <= 2 { 1 } else { 0 };
+	let era = year / 400;
+	let yoe = year - era * 400;
+	let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	era * 146_097 + doe - 719_468
 }
 
 
 impl<T: Trait> Module<T> {
 
+	/// Convert the storage of the older runtime to the current layout, step by step from its version.
+	fn migrate() {
+		let version = Self::storage_version();
+		if version < 1 {
+			Self::migrate_layout();
+		}
+		if version < 2 {
+			Self::migrate_owners();
+		}
+
+		<StorageVersion<T>>::put(STORAGE_VERSION);
+	}
+
+	/// Converts the buckets and the promises to the layout of `STORAGE_VERSION` 1.
+	/// The older promises are paid in the native asset by constant installments of blocks.
+	fn migrate_layout() {
+		let now = <system::Module<T>>::block_number();
+
+		for i in 0..<legacy::AllBucketsCount<T>>::get() {
+			let c2fc_id = <legacy::AllBucketsArray<T>>::get(i);
+			let old = match <legacy::Buckets<T>>::get(c2fc_id) {
+				Some(old) => old,
+				None => continue,
+			};
+
+			let promise = old.promise.map(|old| {
+				// the periods passed before the upgrade are closed without breach,
+				// the filled value was never reset, so it's counted against them:
+				let installment = if old.period.is_zero() {
+					0
+				} else {
+					(now.saturating_sub(old.acception_dt) / old.period).as_() as u32
+				};
+				let filled = old.value.checked_mul(&T::Balance::sa(installment as u64))
+					.map(|due| old.filled.saturating_sub(due))
+					.unwrap_or_else(Zero::zero);
+				Promise {
+					id: old.id,
+					owner: old.owner,
+					value: old.value,
+					asset: NATIVE_ASSET,
+					period: old.period,
+					until: old.until,
+					schedule: Schedule::Constant,
+					clock: Clock::Blocks,
+					filled,
+					acception_dt: old.acception_dt,
+					start_dt: old.acception_dt,
+					installment,
+				}
+			});
+
+			<Buckets<T>>::insert(c2fc_id, Bucket {
+				id: old.id,
+				promise,
+				price: old.price,
+				price_asset: NATIVE_ASSET,
+				creator: <legacy::BucketOwner<T>>::get(c2fc_id).unwrap_or_default(),
+				royalty: Permill::zero(),
+			});
+		}
+
+		for i in 0..Self::free_promises_count() {
+			let promise_id = Self::free_promise_by_index(i);
+			if let Some(old) = <legacy::Promises<T>>::get(promise_id) {
+				<Promises<T>>::insert(promise_id, FreePromise {
+					id: old.id,
+					value: old.value,
+					asset: NATIVE_ASSET,
+					period: old.period,
+					until: old.until,
+					schedule: Schedule::Constant,
+					start: Start::Immediate,
+					clock: Clock::Blocks,
+				});
+			}
+
+			// the older stakes were locked in the native balance, they are unlocked and not moved to AKT,
+			// the promise stays unstaked until the issuer stakes AKT to it:
+			if <LockForPromise<T>>::exists(promise_id) {
+				let lock_id = <LockForPromise<T>>::take(promise_id);
+				if let Some(issuer) = Self::owner_of_promise(promise_id) {
+					let amount = <balances::Module<T>>::locks(&issuer).into_iter()
+						.find(|lock| lock.id == lock_id)
+						.map(|lock| lock.amount)
+						.unwrap_or_default();
+					<balances::Module<T> as LockableCurrency<T::AccountId>>::remove_lock(lock_id, &issuer);
+					Self::deposit_event(RawEvent::LegacyStakeUnlocked(promise_id, issuer, amount));
+				}
+			}
+		}
+	}
+
+	/// Moves the owners of the buckets to the NFT registry of `STORAGE_VERSION` 2.
+	fn migrate_owners() {
+		for i in 0..<legacy::AllBucketsCount<T>>::take() {
+			let c2fc_id = <legacy::AllBucketsArray<T>>::take(i);
+			<legacy::AllBucketsIndex<T>>::remove(c2fc_id);
+
+			if let Some(owner) = <legacy::BucketOwner<T>>::take(c2fc_id) {
+				let index = <legacy::OwnedBucketsIndex<T>>::take(c2fc_id);
+				<legacy::OwnedBucketsArray<T>>::remove((owner.clone(), index));
+				<legacy::OwnedBucketsCount<T>>::remove(&owner);
+
+				// the registry builds its own indices, a bucket minted already is kept as is
+				let _ = BucketRegistry::<T>::mint(&owner, c2fc_id);
+			}
+		}
+	}
+
+	/// Runs the body of the call made by `who`.
+	/// The failure is reported by `Failed` event with the index of the error,
+	/// the event is kept because a failed call isn't reverted.
+	fn report<R, F: FnOnce() -> result::Result<R, Error>>(who: T::AccountId, f: F) -> result::Result<R, &'static str> {
+		f().map_err(|e| {
+			Self::deposit_event(RawEvent::Failed(who, e));
+			e.into()
+		})
+	}
+
 	/// Create LockIdentifier via simple counter `locks_count`.
 	/// Previously was by promise_id.
 	fn next_free_lock_identifier(_promise_id: &T::Hash) -> LockIdentifier {
@@ -571,55 +1630,378 @@ impl<T: Trait> Module<T> {
 		to: T::AccountId,
 		c2fc_id: T::Hash,
 		new_c2fc: Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>,
-	) -> Result {
-		ensure!(!<BucketOwner<T>>::exists(c2fc_id), "Bucket already exists");
+	) -> result::Result<(), Error> {
+		ensure!(BucketRegistry::<T>::owner_of(&c2fc_id).is_none(), Error::BucketExists);
 
-		let owned_c2fc_count = Self::owned_c2fc_count(&to);
+		BucketRegistry::<T>::mint(&to, c2fc_id).map_err(|_| Error::Overflow)?;
+		<Buckets<T>>::insert(c2fc_id, new_c2fc);
 
-		let new_owned_c2fc_count = owned_c2fc_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new c2fc to account balance")?;
+		Ok(())
+	}
 
-		let all_c2fc_count = Self::all_c2fc_count();
+	/// Accept the free promise to the c2fc of `owner`.
+	fn do_accept_promise(owner: &T::AccountId, promise_id: T::Hash, c2fc_id: T::Hash) -> result::Result<(), Error> {
+		ensure!(<Buckets<T>>::exists(c2fc_id), Error::BucketNotFound);
+		ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
+		ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), Error::PromiseAccepted);
+
+		let c2fc_owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+		ensure!(c2fc_owner == *owner, Error::NotBucketOwner);
+
+		let promise_owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+		ensure!(promise_owner != *owner, Error::OwnPromise);
+
+		let mut c2fc = Self::c2fc(c2fc_id);
+		ensure!(c2fc.promise.is_none(), Error::BucketHasPromise);
+
+		// get current (latest) block:
+		let current_block = <system::Module<T>>::block_number();
+
+		let free_promise = Self::promise(promise_id);
+		let start_dt = free_promise.start.start_dt(&free_promise.schedule, free_promise.period, Self::now(&free_promise.clock))?;
+		let promise = Promise {
+			id: free_promise.id,
+			// in the near future `owner` can be removed
+			owner: promise_owner.clone(),
+			value: free_promise.value,
+			asset: free_promise.asset,
+			period: free_promise.period,
+			until: free_promise.until,
+			schedule: free_promise.schedule,
+			clock: free_promise.clock,
+			acception_dt: current_block,
+			start_dt,
+			filled: T::Balance::zero(),
+			installment: 0,
+		};
+		if promise.is_stream() {
+			ensure!(promise.end_dt().map_or(false, |end| end > start_dt), Error::EndsBeforeStart);
+		} else {
+			ensure!(promise.current_installment().is_some(), Error::EndsBeforeStart);
+		}
 
-		let new_all_c2fc_count = all_c2fc_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new c2fc to total supply")?;
+		c2fc.promise = Some(promise);
+		<Buckets<T>>::insert(c2fc_id, c2fc);
+		<AcceptedPromiseBucket<T>>::insert(promise_id, c2fc_id);
 
-		<Buckets<T>>::insert(c2fc_id, new_c2fc);
-		<BucketOwner<T>>::insert(c2fc_id, &to);
+		// incrmnt the counter & push to maps:
+		{
+			let accepted_promises_count = Self::accepted_promises_count();
+			let new_accepted_promises_count = accepted_promises_count
+				.checked_add(1)
+				.ok_or(Error::Overflow)?;
 
-		<AllBucketsArray<T>>::insert(all_c2fc_count, c2fc_id);
-		<AllBucketsCount<T>>::put(new_all_c2fc_count);
-		<AllBucketsIndex<T>>::insert(c2fc_id, all_c2fc_count);
+			<BucketContributor<T>>::insert(c2fc_id, promise_owner);
 
-		<OwnedBucketsArray<T>>::insert((to.clone(), owned_c2fc_count), c2fc_id);
-		<OwnedBucketsCount<T>>::insert(&to, new_owned_c2fc_count);
-		<OwnedBucketsIndex<T>>::insert(c2fc_id, owned_c2fc_count);
+			<AcceptedPromisesArray<T>>::insert(accepted_promises_count, promise_id);
+			<AcceptedPromisesCount<T>>::put(new_accepted_promises_count);
+			<AcceptedPromisesIndex<T>>::insert(promise_id, accepted_promises_count);
+		}
+
+		Ok(())
+	}
+
+	/// Lock `amount` more AKT of the issuer as collateral of the promise.
+	fn do_stake(issuer: &T::AccountId, promise_id: T::Hash, amount: StakeBalanceOf<T>) -> result::Result<(), Error> {
+		ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
+		let owner = Self::owner_of_promise(promise_id).ok_or(Error::NoPromiseOwner)?;
+		ensure!(owner == *issuer, Error::NotPromiseOwner);
+
+		// the own AKT bonded or staked to the other promises can't back this one,
+		// the rest of the amount is held from the AKT delegated to the issuer:
+		let issuer_staked = Self::issuer_staked(issuer).saturating_add(T::Backing::bonded(issuer));
+		let own = rstd::cmp::min(amount, T::Stake::free_balance(issuer).saturating_sub(issuer_staked));
+		let delegated = amount - own;
+		ensure!(delegated <= T::Backing::delegated(issuer), Error::InsufficientStake);
+
+		Self::lock_stake(issuer, promise_id, own, delegated)
+	}
+
+	/// Lock `own` AKT of the issuer and hold `delegated` AKT as collateral of the promise.
+	fn lock_stake(issuer: &T::AccountId, promise_id: T::Hash, own: StakeBalanceOf<T>, delegated: StakeBalanceOf<T>) -> result::Result<(), Error> {
+		// get data from existing promise:
+		let (clock, end) = if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+			let promise = {
+				let c2fc_id = <AcceptedPromiseBucket<T>>::get(promise_id);
+				let c2fc = Self::c2fc(c2fc_id);
+				let promise = c2fc.promise;
+				ensure!(promise.is_some(), Error::BucketWithoutPromise);
+				promise.unwrap()
+			};
+			// lock till the last payment, which is shifted by the deferred start
+			(promise.clock, promise.end_dt())
+		} else {
+			let promise = Self::promise(promise_id);
+			(promise.clock, promise.until)
+		};
+		// end of the universe for endless promises:
+		let until = end.map(|end| Self::block_at(&clock, end)).unwrap_or_else(T::BlockNumber::max_value);
+
+		// the staked AKT can't be transferred or burnt either:
+		let reasons = WithdrawReasons::all();
+
+		let (staked, _) = Self::staked(promise_id);
+		let staked = staked.checked_add(&own).and_then(|s| s.checked_add(&delegated)).ok_or(Error::Overflow)?;
+
+		let issuer_staked = Self::issuer_staked(issuer);
+		let staked_delegated = Self::staked_delegated(promise_id).saturating_add(delegated);
+		let locked = staked - staked_delegated;
+		if <LockForPromise<T>>::exists(promise_id) {
+			let lock_id = Self::lock_for_promise(promise_id);
+			T::Stake::extend_lock(lock_id, issuer, locked, until, reasons);
+		} else {
+			let lock_id = Self::next_free_lock_identifier(&promise_id);
+			T::Stake::set_lock(lock_id, issuer, locked, until, reasons);
+
+			// register new lock:
+			<LockForPromise<T>>::insert(promise_id, lock_id);
+			<LocksCount<T>>::mutate(|n| *n += 1);
+		}
+		if !delegated.is_zero() {
+			T::Backing::hold(issuer, delegated);
+			<StakedDelegated<T>>::insert(promise_id, staked_delegated);
+		}
+		<IssuerStaked<T>>::insert(issuer, issuer_staked.saturating_add(own));
+		<Staked<T>>::insert(promise_id, (staked, until));
+
+		Ok(())
+	}
+
+	/// Unlock the stake of the promise and release the held delegated AKT, returns the staked amount.
+	fn unstake(issuer: &T::AccountId, promise_id: T::Hash) -> StakeBalanceOf<T> {
+		let lock_id = <LockForPromise<T>>::take(promise_id);
+		let (staked, _) = <Staked<T>>::take(promise_id);
+		let delegated = <StakedDelegated<T>>::take(promise_id);
+		T::Stake::remove_lock(lock_id, issuer);
+
+		<IssuerStaked<T>>::mutate(issuer, |v| *v = v.saturating_sub(staked - delegated));
+		if !delegated.is_zero() {
+			T::Backing::release(issuer, delegated);
+		}
+		staked
+	}
+
+	/// (own, delegated) parts of the stake of the promise, if it's staked.
+	fn stake_of(promise_id: T::Hash) -> Option<(StakeBalanceOf<T>, StakeBalanceOf<T>)> {
+		if !<LockForPromise<T>>::exists(promise_id) {
+			return None;
+		}
+		let (staked, _) = Self::staked(promise_id);
+		let delegated = Self::staked_delegated(promise_id);
+		Some((staked - delegated, delegated))
+	}
+
+	/// Slash the stake of the breached promise, if any.
+	fn breach_stake(promise_id: T::Hash, issuer: &T::AccountId) {
+		if let Some((own, delegated)) = Self::stake_of(promise_id) {
+			let (own_slashed, delegated_slashed) = T::Backing::on_breach(promise_id, issuer, own, delegated);
+			let own_slashed = rstd::cmp::min(own_slashed, own);
+			let delegated_slashed = rstd::cmp::min(delegated_slashed, delegated);
+
+			// the slashed AKT don't back the promise anymore:
+			let (staked, until) = Self::staked(promise_id);
+			<Staked<T>>::insert(promise_id, (staked - own_slashed - delegated_slashed, until));
+			if !delegated_slashed.is_zero() {
+				<StakedDelegated<T>>::insert(promise_id, delegated - delegated_slashed);
+			}
+			if !own_slashed.is_zero() {
+				<IssuerStaked<T>>::mutate(issuer, |v| *v = v.saturating_sub(own_slashed));
+				T::Stake::set_lock(Self::lock_for_promise(promise_id), issuer, own - own_slashed, until, WithdrawReasons::all());
+			}
+		}
+	}
+
+	fn do_create_promise(
+		issuer: T::AccountId,
+		promise_id: T::Hash,
+		value: T::Balance,
+		asset: AssetId,
+		period: T::BlockNumber,
+		until: T::BlockNumber,
+		schedule: Schedule<T::Balance, T::BlockNumber>,
+		start: Start<T::BlockNumber>,
+		clock: Clock,
+	) -> result::Result<(), Error> {
+		let until = if !until.is_zero() { Some(until) } else { None };
+		ensure!(<token::Module<T>>::asset_exists(asset), Error::AssetNotFound);
+		if let Schedule::Stream = schedule {
+			ensure!(asset == NATIVE_ASSET, Error::NotNativeAsset);
+		}
+		schedule.validate(period, until, Self::parameters().max_installments)?;
+		start.validate(&schedule)?;
+		Self::check_limits(value, period, &schedule, &clock)?;
+
+		let max_promises = Self::parameters().max_promises_per_account;
+		ensure!(max_promises == 0 || Self::owned_promise_count(&issuer) < max_promises, Error::TooManyPromises);
+
+		let new_promise = FreePromise {
+			id: promise_id,
+			value,
+			asset,
+			period,
+			until,
+			schedule,
+			start,
+			clock,
+		};
+
+		Self::mint_promise(issuer, promise_id, new_promise)
+	}
+
+	/// Check the promise terms against the market parameters.
+	fn check_limits(value: T::Balance, period: T::BlockNumber, schedule: &Schedule<T::Balance, T::BlockNumber>, clock: &Clock) -> result::Result<(), Error> {
+		let params = Self::parameters();
 
-		Self::deposit_event(RawEvent::C2fcCreated(to, c2fc_id));
+		if !params.max_value.is_zero() {
+			ensure!(value <= params.max_value, Error::ValueAboveLimit);
+			if let Schedule::Installments(list) = schedule {
+				ensure!(list.iter().all(|&(_, amount)| amount <= params.max_value), Error::ValueAboveLimit);
+			}
+		}
+
+		// the period of single payment promises doesn't matter:
+		match schedule {
+			Schedule::Constant | Schedule::StepUp(_) | Schedule::StepDown(_) => {
+				let blocks = match clock.to_seconds(period.as_()) {
+					Some(secs) => secs / Self::block_duration(),
+					None => period.as_(),
+				};
+				ensure!(blocks >= params.min_period.as_(), Error::PeriodBelowLimit);
+			},
+			_ => {},
+		}
 
 		Ok(())
 	}
 
+	/// Burn the creation fee of the sender.
+	fn charge_creation_fee(who: &T::AccountId) -> result::Result<(), Error> {
+		let fee = Self::parameters().creation_fee;
+		if !fee.is_zero() {
+			<balances::Module<T> as Currency<T::AccountId>>::withdraw(who, fee, WithdrawReason::Fee, ExistenceRequirement::KeepAlive)
+				.map_err(|_| Error::InsufficientBalance)?;
+		}
+		Ok(())
+	}
+
+	/// Check the creation fee and the storage deposit can be paid keeping the account alive,
+	/// so nothing fails after the c2fc or the promise is created.
+	fn ensure_can_deposit(who: &T::AccountId) -> result::Result<(), Error> {
+		let params = Self::parameters();
+		let needed = params.storage_deposit.saturating_add(params.creation_fee);
+		let keep = <balances::Module<T> as Currency<T::AccountId>>::minimum_balance();
+		ensure!(<balances::Module<T>>::free_balance(who) >= needed.saturating_add(keep), Error::InsufficientBalance);
+		ensure!(<balances::Module<T> as ReservableCurrency<T::AccountId>>::can_reserve(who, needed), Error::InsufficientBalance);
+		Ok(())
+	}
+
+	/// Reserve the storage deposit of the c2fc or the promise.
+	fn reserve_deposit(who: &T::AccountId, id: T::Hash) -> result::Result<(), Error> {
+		let deposit = Self::parameters().storage_deposit;
+		if !deposit.is_zero() {
+			<balances::Module<T> as ReservableCurrency<T::AccountId>>::reserve(who, deposit)
+				.map_err(|_| Error::InsufficientBalance)?;
+			<StorageDeposit<T>>::insert(id, (who.clone(), deposit));
+		}
+		Ok(())
+	}
+
+	/// Return the storage deposit of the c2fc or the promise to the depositor.
+	fn refund_deposit(id: T::Hash) {
+		if let Some((who, deposit)) = <StorageDeposit<T>>::take(id) {
+			<balances::Module<T> as ReservableCurrency<T::AccountId>>::unreserve(&who, deposit);
+		}
+	}
+
+	/// Delete the c2fc from all the maps. The promise of the c2fc should be removed before.
+	fn remove_c2fc(owner: T::AccountId, c2fc_id: T::Hash) {
+		// the c2fc has the owner, so it can not fail
+		let _ = BucketRegistry::<T>::burn(c2fc_id);
+
+		<Buckets<T>>::remove(c2fc_id);
+		<BucketContributor<T>>::remove(c2fc_id);
+		<BucketApproval<T>>::remove(c2fc_id);
+
+		Self::refund_deposit(c2fc_id);
+
+		Self::deposit_event(RawEvent::C2fcClosed(owner, c2fc_id));
+	}
+
+	/// Delete the promise from all the maps, unlock the stake and return the escrow.
+	/// The c2fc of the accepted promise is not changed.
+	fn remove_promise(issuer: T::AccountId, promise_id: T::Hash) {
+		// "Swap and pop" from the free promises
+		let index = <FreePromisesIndex<T>>::take(promise_id);
+		let last = Self::free_promises_count().saturating_sub(1);
+		if index != last {
+			let last_promise_id = <FreePromisesArray<T>>::get(last);
+			<FreePromisesArray<T>>::insert(index, last_promise_id);
+			<FreePromisesIndex<T>>::insert(last_promise_id, index);
+		}
+		<FreePromisesArray<T>>::remove(last);
+		<FreePromisesCount<T>>::put(last);
+
+		// "Swap and pop" from the promises of the issuer
+		let index = <OwnedPromisesIndex<T>>::take(promise_id);
+		let last = Self::owned_promise_count(&issuer).saturating_sub(1);
+		if index != last {
+			let last_promise_id = <OwnedPromisesArray<T>>::get((issuer.clone(), last));
+			<OwnedPromisesArray<T>>::insert((issuer.clone(), index), last_promise_id);
+			<OwnedPromisesIndex<T>>::insert(last_promise_id, index);
+		}
+		<OwnedPromisesArray<T>>::remove((issuer.clone(), last));
+		<OwnedPromisesCount<T>>::insert(&issuer, last);
+
+		// "Swap and pop" from the accepted promises
+		if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+			let index = <AcceptedPromisesIndex<T>>::take(promise_id);
+			let last = Self::accepted_promises_count().saturating_sub(1);
+			if index != last {
+				let last_promise_id = <AcceptedPromisesArray<T>>::get(last);
+				<AcceptedPromisesArray<T>>::insert(index, last_promise_id);
+				<AcceptedPromisesIndex<T>>::insert(last_promise_id, index);
+			}
+			<AcceptedPromisesArray<T>>::remove(last);
+			<AcceptedPromisesCount<T>>::put(last);
+			<AcceptedPromiseBucket<T>>::remove(promise_id);
+		}
+
+		if <LockForPromise<T>>::exists(promise_id) {
+			Self::unstake(&issuer, promise_id);
+		}
+
+		<TotalArrears<T>>::remove(promise_id);
+		let escrow = <Escrow<T>>::take(promise_id);
+		if !escrow.is_zero() {
+			<balances::Module<T> as ReservableCurrency<T::AccountId>>::unreserve(&issuer, escrow);
+		}
+
+		<Promises<T>>::remove(promise_id);
+		<PromiseOwner<T>>::remove(promise_id);
+
+		Self::refund_deposit(promise_id);
+
+		Self::deposit_event(RawEvent::PromiseClosed(issuer, promise_id));
+	}
+
 	fn mint_promise(
 		to: T::AccountId,
 		promise_id: T::Hash,
 		new_promise: FreePromise<T::Hash, T::Balance, T::BlockNumber>,
-	) -> Result {
-		ensure!(!<PromiseOwner<T>>::exists(promise_id), "Promise already exists");
+	) -> result::Result<(), Error> {
+		ensure!(!<PromiseOwner<T>>::exists(promise_id), Error::PromiseExists);
 
 		let owned_promise_count = Self::owned_promise_count(&to);
 
 		let new_owned_promise_count = owned_promise_count
 			.checked_add(1)
-			.ok_or("Overflow adding a new promise to account balance")?;
+			.ok_or(Error::Overflow)?;
 
 		let free_promises_count = Self::free_promises_count();
 
 		let new_free_promises_count = free_promises_count
 			.checked_add(1)
-			.ok_or("Overflow adding a new promise to total supply")?;
+			.ok_or(Error::Overflow)?;
 
 		<Promises<T>>::insert(promise_id, new_promise);
 		<PromiseOwner<T>>::insert(promise_id, &to);
@@ -632,63 +2014,314 @@ impl<T: Trait> Module<T> {
 		<OwnedPromisesCount<T>>::insert(&to, new_owned_promise_count);
 		<OwnedPromisesIndex<T>>::insert(promise_id, owned_promise_count);
 
-		Self::deposit_event(RawEvent::PromiseCreated(to, promise_id));
+		Ok(())
+	}
+
+	/// Whether `who` owns the c2fc or is approved to transfer it.
+	fn is_approved_or_owner(who: &T::AccountId, c2fc_id: T::Hash) -> bool {
+		match Self::owner_of_c2fc(c2fc_id) {
+			Some(owner) => *who == owner
+				|| Self::approved_of_c2fc(c2fc_id).as_ref() == Some(who)
+				|| Self::is_approved_for_all((owner, who.clone())),
+			None => false,
+		}
+	}
+
+	fn do_transfer(from: T::AccountId, to: T::AccountId, c2fc_id: T::Hash) -> result::Result<(), Error> {
+		let owner = Self::owner_of_c2fc(c2fc_id).ok_or(Error::NoBucketOwner)?;
+
+		ensure!(owner == from, Error::NotBucketOwner);
+		ensure!(BucketRegistry::<T>::balance_of(&to).checked_add(1).is_some(), Error::Overflow);
+
+		// previous owner keeps the value accrued while owning the c2fc
+		Self::checkpoint_stream(c2fc_id, &from);
+
+		BucketRegistry::<T>::transfer(&from, &to, c2fc_id).map_err(|_| Error::Overflow)?;
+		// the new owner is bound neither by the approval nor by the price of the previous one
+		<BucketApproval<T>>::remove(c2fc_id);
+		<Buckets<T>>::mutate(c2fc_id, |c2fc| c2fc.price = T::Balance::zero());
+
+		Self::deposit_event(RawEvent::Transferred(from, to, c2fc_id));
 
 		Ok(())
 	}
 
-	fn transfer_from(from: T::AccountId, to: T::AccountId, c2fc_id: T::Hash) -> Result {
-		let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+	/// Pays up to `amount` from the escrow of the promise to `to`, the `payment_fee` of it goes to the treasury.
+	/// Returns the paid value, including the fee.
+	fn pay_from_escrow(c2fc_id: T::Hash, promise_id: T::Hash, issuer: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let escrow = Self::escrow_of(promise_id);
+		if escrow.is_zero() {
+			return Zero::zero();
+		}
 
-		ensure!(owner == from, "'from' account does not own this c2fc");
+		let value = rstd::cmp::min(escrow, amount);
+		let fee = Self::parameters().payment_fee * value;
+		// `repatriate_reserved` returns the part which could not be moved
+		let moved = match <balances::Module<T> as ReservableCurrency<T::AccountId>>::repatriate_reserved(issuer, to, value - fee) {
+			Ok(left) => value - fee - left,
+			Err(_) => return Zero::zero(),
+		};
+		let (fee_paid, _) = <balances::Module<T> as ReservableCurrency<T::AccountId>>::slash_reserved(issuer, fee);
+		let fee = fee_paid.peek();
+		T::ProtocolFee::on_unbalanced(fee_paid);
+		if !fee.is_zero() {
+			Self::deposit_event(RawEvent::FeePaid(c2fc_id, issuer.clone(), fee));
+		}
 
-		let owned_c2fc_count_from = Self::owned_c2fc_count(&from);
-		let owned_c2fc_count_to = Self::owned_c2fc_count(&to);
+		let paid = moved + fee;
+		<Escrow<T>>::insert(promise_id, escrow - paid);
+		paid
+	}
 
-		let new_owned_c2fc_count_to = owned_c2fc_count_to
-			.checked_add(1)
-			.ok_or("Transfer causes overflow of 'to' c2fc balance")?;
+	/// Pays the value of the streaming promise accrued by now to the `owner` of the c2fc.
+	/// What the escrow can't pay is kept as arrears of the `owner`.
+	fn checkpoint_stream(c2fc_id: T::Hash, owner: &T::AccountId) {
+		let mut c2fc = Self::c2fc(c2fc_id);
+		if let Some(ref mut promise) = c2fc.promise {
+			if !promise.is_stream() {
+				return;
+			}
 
-		let new_owned_c2fc_count_from = owned_c2fc_count_from
-			.checked_sub(1)
-			.ok_or("Transfer causes underflow of 'from' c2fc balance")?;
+			let accrued = promise.accrued(Self::now(&promise.clock));
+			if accrued <= promise.filled {
+				return;
+			}
 
-		// "Swap and pop"
-		let c2fc_index = <OwnedBucketsIndex<T>>::get(c2fc_id);
-		if c2fc_index != new_owned_c2fc_count_from {
-			let last_c2fc_id = <OwnedBucketsArray<T>>::get((from.clone(), new_owned_c2fc_count_from));
-			<OwnedBucketsArray<T>>::insert((from.clone(), c2fc_index), last_c2fc_id);
-			<OwnedBucketsIndex<T>>::insert(last_c2fc_id, c2fc_index);
+			let promise_id = promise.id;
+			let due = accrued - promise.filled;
+			let paid = Self::pay_from_escrow(c2fc_id, promise_id, &promise.owner, owner, due);
+			if paid < due {
+				// the stake is slashed once for the shortfall, not on every checkpoint until the arrears are paid
+				let new_shortfall = Self::total_arrears(promise_id).is_zero();
+				<StreamArrears<T>>::mutate((promise_id, owner.clone()), |arrears| *arrears = arrears.saturating_add(due - paid));
+				<TotalArrears<T>>::mutate(promise_id, |arrears| *arrears = arrears.saturating_add(due - paid));
+				Self::deposit_event(RawEvent::PromiseBreached(c2fc_id, promise_id, due - paid));
+				if new_shortfall {
+					Self::breach_stake(promise_id, &promise.owner);
+				}
+			}
+			promise.filled = accrued;
+
+			Self::deposit_event(RawEvent::StreamClaimed(c2fc_id, promise_id, owner.clone(), paid));
 		}
+		<Buckets<T>>::insert(c2fc_id, c2fc);
+	}
 
-		<BucketOwner<T>>::insert(&c2fc_id, &to);
-		<OwnedBucketsIndex<T>>::insert(c2fc_id, owned_c2fc_count_to);
+	fn transfer_money(asset: AssetId, from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> result::Result<(), Error> {
+		<token::Module<T>>::transfer_asset(asset, from, to, amount).map_err(|_| Error::TransferFailed)
+	}
 
-		<OwnedBucketsArray<T>>::remove((from.clone(), new_owned_c2fc_count_from));
-		<OwnedBucketsArray<T>>::insert((to.clone(), owned_c2fc_count_to), c2fc_id);
+	/// Royalty of the creator when the c2fc is sold by the `seller` for the `price`, the creator pays none.
+	fn royalty_of(c2fc: &Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>, seller: &T::AccountId, price: T::Balance) -> T::Balance {
+		if *seller == c2fc.creator {
+			return Zero::zero();
+		}
+		(c2fc.royalty * price).min(price)
+	}
 
-		<OwnedBucketsCount<T>>::insert(&from, new_owned_c2fc_count_from);
-		<OwnedBucketsCount<T>>::insert(&to, new_owned_c2fc_count_to);
+	/// Pay `amount` of the asset, the `royalty` part of it goes to its account
+	/// and the `rate` of the rest goes to the treasury of the asset.
+	/// Either the whole amount is paid or nothing.
+	fn pay_with_fee(c2fc_id: T::Hash, asset: AssetId, from: &T::AccountId, to: &T::AccountId, amount: T::Balance, rate: Permill, royalty: (&T::AccountId, T::Balance)) -> result::Result<(), Error> {
+		let (creator, royalty) = royalty;
+		let fee = rate * (amount - royalty);
+		if fee.is_zero() && royalty.is_zero() {
+			return Self::transfer_money(asset, from, to, amount);
+		}
 
-		Self::deposit_event(RawEvent::Transferred(from, to, c2fc_id));
+		if asset != NATIVE_ASSET {
+			let treasury = if fee.is_zero() { None } else { Some(Self::asset_treasury(asset).ok_or(Error::NoAssetTreasury)?) };
+			// issued assets have no existential deposit, so all the transfers pass when the whole amount does
+			ensure!(<token::Module<T>>::free_balance_of(asset, from) >= amount, Error::InsufficientBalance);
+			Self::transfer_money(asset, from, creator, royalty)?;
+			if let Some(treasury) = treasury {
+				Self::transfer_money(asset, from, &treasury, fee)?;
+				Self::deposit_event(RawEvent::FeePaid(c2fc_id, from.clone(), fee));
+			}
+			return Self::transfer_money(asset, from, to, amount - royalty - fee);
+		}
+
+		// the recipient can't be created by less than the existential deposit:
+		let value = amount - royalty - fee;
+		ensure!(
+			value.is_zero()
+				|| value >= <balances::Module<T> as Currency<T::AccountId>>::minimum_balance()
+				|| !<balances::Module<T> as Currency<T::AccountId>>::total_balance(to).is_zero(),
+			Error::TransferFailed
+		);
+
+		// single withdrawal checks the existential deposit and the balance for all the parts
+		let paid = <balances::Module<T> as Currency<T::AccountId>>::withdraw(from, amount, WithdrawReason::Transfer, ExistenceRequirement::KeepAlive)
+			.map_err(|_| Error::TransferFailed)?;
+		let (royalty_paid, rest) = paid.split(royalty);
+		let (fee_paid, rest) = rest.split(fee);
+		// the royalty which the reaped creator can't receive goes to the recipient
+		let rest = match royalty_paid.offset(<balances::Module<T> as Currency<T::AccountId>>::deposit_creating(creator, royalty)) {
+			Ok(left) => rest.merge(left),
+			Err(_) => rest,
+		};
+		let value = rest.peek();
+		// only the fee leaves the accounts, what the recipient can't receive returns to the payer,
+		// which is kept alive by the withdrawal:
+		if let Ok(left) = rest.offset(<balances::Module<T> as Currency<T::AccountId>>::deposit_creating(to, value)) {
+			let refund = left.peek();
+			let _ = left.offset(<balances::Module<T> as Currency<T::AccountId>>::deposit_creating(from, refund));
+		}
+		T::ProtocolFee::on_unbalanced(fee_paid);
 
+		if !fee.is_zero() {
+			Self::deposit_event(RawEvent::FeePaid(c2fc_id, from.clone(), fee));
+		}
 		Ok(())
 	}
 
-	fn transfer_money(from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> Result {
-		<balances::Module<T> as Currency<T::AccountId>>::transfer(&from, &to, amount)
+
+	// utilites //
+
+	/// Returns the accepted promise with specified id.
+	pub fn accepted_promise(promise_id: T::Hash) -> Option<Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		if !<AcceptedPromiseBucket<T>>::exists(promise_id) {
+			return None;
+		}
+		Self::c2fc(Self::c2fc_by_promise(promise_id)).promise
 	}
 
+	/// Amount that should be filled to the promise to avoid any breach up to block `at`:
+	/// sum of the installments due by `at` minus the already filled value.
+	pub fn due_amount(promise_id: T::Hash, at: T::BlockNumber) -> T::Balance {
+		let promise = match Self::accepted_promise(promise_id) {
+			Some(promise) => promise,
+			None => return Zero::zero(),
+		};
+		let at = Self::time_at(&promise.clock, at);
+
+		if promise.is_stream() {
+			return promise.accrued(at).saturating_sub(promise.filled);
+		}
 
-	// utilites //
+		let mut due = T::Balance::zero();
+		for k in 0..MAX_DUE_LOOKAHEAD {
+			match promise.installment_ahead(k) {
+				Some((due_dt, amount)) if due_dt <= at => due = due.saturating_add(amount),
+				_ => break,
+			}
+		}
+		due.saturating_sub(promise.filled)
+	}
+
+	/// Current time in units of the `clock`.
+	pub fn now(clock: &Clock) -> T::BlockNumber {
+		let secs: u64 = <timestamp::Module<T>>::now().as_();
+		match clock.from_seconds(secs) {
+			Some(time) => T::BlockNumber::sa(time),
+			None => <system::Module<T>>::block_number(),
+		}
+	}
+
+	/// Expected duration of a block in seconds.
+	fn block_duration() -> u64 {
+		// Aura slot is twice the minimum period of the timestamp
+		rstd::cmp::max(<timestamp::Module<T>>::minimum_period().as_() * 2, 1)
+	}
+
+	/// Estimates time in units of the `clock` at the specified block.
+	pub fn time_at(clock: &Clock, block: T::BlockNumber) -> T::BlockNumber {
+		let current_block: u64 = <system::Module<T>>::block_number().as_();
+		let block_number: u64 = block.as_();
+		let secs: u64 = <timestamp::Module<T>>::now().as_();
+		let secs = if block_number >= current_block {
+			secs.saturating_add((block_number - current_block).saturating_mul(Self::block_duration()))
+		} else {
+			secs.saturating_sub((current_block - block_number).saturating_mul(Self::block_duration()))
+		};
+		match clock.from_seconds(secs) {
+			Some(time) => T::BlockNumber::sa(time),
+			None => block,
+		}
+	}
+
+	/// Estimates the block when the `clock` reaches the specified time.
+	pub fn block_at(clock: &Clock, time: T::BlockNumber) -> T::BlockNumber {
+		let secs = match clock.to_seconds(time.as_()) {
+			Some(secs) => secs,
+			None => return time,
+		};
+		let current_block = <system::Module<T>>::block_number();
+		let now: u64 = <timestamp::Module<T>>::now().as_();
+		if secs <= now {
+			return current_block;
+		}
+		let duration = Self::block_duration();
+		let blocks = (secs - now + duration - 1) / duration;
+		current_block.saturating_add(T::BlockNumber::sa(blocks))
+	}
+
+	/// Returns the c2fc with specified id.
+	pub fn bucket(c2fc_id: T::Hash) -> Option<Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		if !<Buckets<T>>::exists(c2fc_id) {
+			return None;
+		}
+		Some(Self::c2fc(c2fc_id))
+	}
+
+	/// Returns the promise with specified id in the state it was created.
+	pub fn free_promise(promise_id: T::Hash) -> Option<FreePromise<T::Hash, T::Balance, T::BlockNumber>> {
+		if !<Promises<T>>::exists(promise_id) {
+			return None;
+		}
+		Some(Self::promise(promise_id))
+	}
+
+	/// Owner of the c2fc.
+	pub fn owner_of_c2fc(c2fc_id: T::Hash) -> Option<T::AccountId> {
+		BucketRegistry::<T>::owner_of(&c2fc_id)
+	}
+
+	/// Ids of all c2fc owned by `who`.
+	pub fn buckets_of(who: &T::AccountId) -> Vec<T::Hash> {
+		(0..BucketRegistry::<T>::balance_of(who))
+			.map(|i| BucketRegistry::<T>::token_of_owner_by_index(who, i))
+			.collect()
+	}
+
+	/// Ids of all promises issued by `who`.
+	pub fn promises_of(who: &T::AccountId) -> Vec<T::Hash> {
+		(0..Self::owned_promise_count(who))
+			.map(|i| Self::promise_of_owner_by_index((who.clone(), i)))
+			.collect()
+	}
+
+	/// Ids of promises matching the `filter`, `PROMISES_PAGE_SIZE` per page.
+	pub fn free_promises(page: u32, filter: PromiseFilter<T::AccountId, T::Balance>) -> Vec<T::Hash> {
+		let skip = (page as usize).saturating_mul(PROMISES_PAGE_SIZE as usize);
+		(0..Self::free_promises_count())
+			.map(Self::free_promise_by_index)
+			.filter(|promise_id| {
+				let promise = Self::promise(promise_id);
+				(filter.with_accepted || !<AcceptedPromiseBucket<T>>::exists(promise_id))
+					&& filter.issuer.as_ref().map_or(true, |issuer| Self::owner_of_promise(promise_id).as_ref() == Some(issuer))
+					&& filter.min_value.map_or(true, |min| promise.value >= min)
+					&& filter.max_value.map_or(true, |max| promise.value <= max)
+					&& filter.asset.map_or(true, |asset| promise.asset == asset)
+			})
+			.skip(skip)
+			.take(PROMISES_PAGE_SIZE as usize)
+			.collect()
+	}
 
 	#[inline]
-	pub fn is_promise_accepted(promise_id: T::Hash) -> result::Result<bool, &'static str> {
-		ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+	pub fn is_promise_accepted(promise_id: T::Hash) -> result::Result<bool, Error> {
+		ensure!(<Promises<T>>::exists(promise_id), Error::PromiseNotFound);
 		Ok(<AcceptedPromiseBucket<T>>::exists(promise_id))
 	}
 }
 
+impl<T: Trait> Collateral<T::AccountId, StakeBalanceOf<T>> for Module<T> {
+	fn staked(who: &T::AccountId) -> StakeBalanceOf<T> {
+		Self::issuer_staked(who)
+	}
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -696,17 +2329,28 @@ mod tests {
 
 	use runtime_io::with_externalities;
 	use primitives::{H256, Blake2Hasher};
-	use support::{impl_outer_origin, assert_ok};
+	use support::{impl_outer_origin, impl_outer_event, assert_ok, assert_err};
 	use runtime_primitives::{
 		BuildStorage,
-		traits::{BlakeTwo256, IdentityLookup},
+		traits::{BlakeTwo256, IdentityLookup, OnFinalize, OnInitialize},
 		testing::{Digest, DigestItem, Header}
-};
+	};
+	use crate::stake;
 
 	impl_outer_origin! {
 		pub enum Origin for Test {}
 	}
 
+	mod c2fc {
+		pub use crate::c2fc::Event;
+	}
+
+	impl_outer_event! {
+		pub enum TestEvent for Test {
+			balances<T>, token<T>, stake<T>, c2fc<T>,
+		}
+	}
+
 	// For testing the module, we construct most of a mock runtime. This means
 	// first constructing a configuration type (`Test`) which `impl`s each of the
 	// configuration traits of modules we want to use.
@@ -722,32 +2366,768 @@ mod tests {
 		type AccountId = u64;
 		type Lookup = IdentityLookup<Self::AccountId>;
 		type Header = Header;
-		type Event = ();
+		type Event = TestEvent;
 		type Log = DigestItem;
 	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = TestEvent;
+		type TransactionPayment = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+	}
+	impl timestamp::Trait for Test {
+		type Moment = u64;
+		type OnTimestampSet = ();
+	}
+	impl token::Trait for Test {
+		type Event = TestEvent;
+	}
+	impl nft::Trait for Test {}
+	impl stake::Trait for Test {
+		type Balance = u64;
+		type Collateral = Cashflow;
+		type Event = TestEvent;
+	}
 	impl Trait for Test {
-		type Event = ();
+		type Stake = Akt;
+		type OnPeriodPaid = Akt;
+		type Backing = Akt;
+		type ProtocolFee = ();
+		type Event = TestEvent;
 	}
-	type CashflowModule = Module<Test>;
+
+	type Cashflow = Module<Test>;
+	type Balances = balances::Module<Test>;
+	type Akt = stake::Module<Test>;
+	type System = system::Module<Test>;
+	type Timestamp = timestamp::Module<Test>;
+	type Token = token::Module<Test>;
+
+	/// Issuer of the promises.
+	const ISSUER: u64 = 1;
+	/// Owner of the buckets.
+	const OWNER: u64 = 2;
+	/// Third party: buyer, payer or delegator.
+	const OTHER: u64 = 3;
+
+	/// Native and AKT balance of every account at genesis.
+	const ENDOWMENT: u64 = 1_000_000;
+	/// Part of the stake slashed on breach, in millionths.
+	const SLASH_RATE: u32 = 100_000;
+	/// Expected duration of a block in seconds, twice the minimum period of the timestamp.
+	const BLOCK_SECS: u64 = 6;
 
 	// This function basically just builds a genesis storage key/value store according to
 	// our desired mockup.
 	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-		system::GenesisConfig::<Test>::default()
-			.build_storage()
-			.unwrap()
-			.0
-			.into()
+		new_test_ext_with(GenesisConfig::<Test>::default())
+	}
+
+	/// The genesis of the module is built on top of the others, as in the runtime.
+	fn new_test_ext_with(config: GenesisConfig<Test>) -> runtime_io::TestExternalities<Blake2Hasher> {
+		let endowed = vec![(ISSUER, ENDOWMENT), (OWNER, ENDOWMENT), (OTHER, ENDOWMENT)];
+
+		let (mut t, mut c) = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		timestamp::GenesisConfig::<Test> {
+			minimum_period: BLOCK_SECS / 2,
+		}.assimilate_storage(&mut t, &mut c).unwrap();
+		balances::GenesisConfig::<Test> {
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit: 1,
+			transfer_fee: 0,
+			creation_fee: 0,
+			balances: endowed.clone(),
+			vesting: vec![],
+		}.assimilate_storage(&mut t, &mut c).unwrap();
+		stake::GenesisConfig::<Test> {
+			balances: endowed,
+			unbonding_duration: 10,
+			max_issuance: 10 * ENDOWMENT,
+			era_length: 100,
+			reward_rate: 0,
+			inflation_rewards: false,
+			slash_rate: SLASH_RATE,
+		}.assimilate_storage(&mut t, &mut c).unwrap();
+		config.assimilate_storage(&mut t, &mut c).unwrap();
+		t.into()
+	}
+
+	/// Creates the promise of `ISSUER` and returns its id.
+	fn create_promise(value: u64, period: u64, until: u64, schedule: Schedule<u64, u64>) -> H256 {
+		assert_ok!(Cashflow::create_scheduled_promise(Origin::signed(ISSUER), value, period, until, schedule, Start::Immediate, Clock::Blocks));
+		*Cashflow::promises_of(&ISSUER).last().expect("promise is created")
+	}
+
+	/// Creates the bucket of `OWNER` and returns its id.
+	fn create_bucket() -> H256 {
+		assert_ok!(Cashflow::create_c2fc(Origin::signed(OWNER)));
+		*Cashflow::buckets_of(&OWNER).last().expect("bucket is created")
+	}
+
+	/// Creates the promise and accepts it to a new bucket of `OWNER`, returns (bucket, promise).
+	fn accepted_promise(value: u64, period: u64, until: u64, schedule: Schedule<u64, u64>) -> (H256, H256) {
+		let promise_id = create_promise(value, period, until, schedule);
+		let c2fc_id = create_bucket();
+		assert_ok!(Cashflow::accept_promise(Origin::signed(OWNER), promise_id, c2fc_id));
+		(c2fc_id, promise_id)
+	}
+
+	/// Runs `on_finalize` of the blocks up to `n`, the timestamp advances by `BLOCK_SECS` per block.
+	/// Whether the event of the module is deposited.
+	fn deposited(event: RawEvent<u64, H256, u64, u64>) -> bool {
+		System::events().iter().any(|record| record.event == TestEvent::c2fc(event.clone()))
+	}
+
+	fn run_to_block(n: u64) {
+		while System::block_number() < n {
+			let next = System::block_number() + 1;
+			System::set_block_number(next);
+			Timestamp::set_timestamp(next * BLOCK_SECS);
+			<Cashflow as OnFinalize<u64>>::on_finalize(next);
+		}
+	}
+
+	/// Day since the unix epoch of the date.
+	fn day(year: u64, month: u64, day: u64) -> u64 {
+		month_first_day((year - 1970) * 12 + month - 1) + day - 1
+	}
+
+	#[test]
+	fn month_index_rolls_over_months_and_years() {
+		assert_eq!(month_index(0), 0);
+		assert_eq!(month_index(30), 0);
+		assert_eq!(month_index(31), 1);
+		assert_eq!(month_index(18_261), 599);
+		assert_eq!(month_index(18_262), 600);
+
+		assert_eq!(month_first_day(0), 0);
+		assert_eq!(month_first_day(1), 31);
+		assert_eq!(month_first_day(600), 18_262);
+	}
+
+	#[test]
+	fn month_index_handles_leap_years() {
+		// 1972 and 2000 are leap years, 2100 is not
+		assert_eq!(month_index(789), 25);
+		assert_eq!(month_index(790), 26);
+		assert_eq!(month_index(11_016), 361);
+		assert_eq!(month_index(11_017), 362);
+		assert_eq!(month_index(47_540), 1561);
+		assert_eq!(month_index(47_541), 1562);
+
+		assert_eq!(day(2000, 3, 1) - day(2000, 2, 1), 29);
+		assert_eq!(day(2100, 3, 1) - day(2100, 2, 1), 28);
+		assert_eq!(day(2001, 1, 1) - day(2000, 1, 1), 366);
+	}
+
+	#[test]
+	fn month_first_day_round_trips() {
+		for index in 1..2_400 {
+			let first = month_first_day(index);
+			assert_eq!(month_index(first), index);
+			assert_eq!(month_index(first - 1), index - 1);
+			let days = first - month_first_day(index - 1);
+			assert!(days >= 28 && days <= 31);
+		}
+	}
+
+	#[test]
+	fn clocks_convert_to_seconds_and_back() {
+		assert_eq!(Clock::Blocks.from_seconds(100), None);
+		assert_eq!(Clock::Blocks.to_seconds(100), None);
+		assert_eq!(Clock::Seconds.to_seconds(100), Some(100));
+
+		let march = Clock::Months.to_seconds(362).unwrap();
+		assert_eq!(march, 11_017 * SECS_PER_DAY);
+		assert_eq!(Clock::Months.from_seconds(march), Some(362));
+		assert_eq!(Clock::Months.from_seconds(march - 1), Some(361));
+	}
+
+	#[test]
+	fn time_at_estimates_by_block_duration() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(10);
+			Timestamp::set_timestamp(1_000);
+
+			assert_eq!(Cashflow::time_at(&Clock::Blocks, 15), 15);
+			assert_eq!(Cashflow::time_at(&Clock::Seconds, 10), 1_000);
+			assert_eq!(Cashflow::time_at(&Clock::Seconds, 15), 1_000 + 5 * BLOCK_SECS);
+			assert_eq!(Cashflow::time_at(&Clock::Seconds, 5), 1_000 - 5 * BLOCK_SECS);
+			assert_eq!(Cashflow::time_at(&Clock::Seconds, 0), 1_000 - 10 * BLOCK_SECS);
+		});
+	}
+
+	#[test]
+	fn block_at_rounds_up_and_round_trips() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(10);
+			Timestamp::set_timestamp(1_000);
+
+			assert_eq!(Cashflow::block_at(&Clock::Blocks, 42), 42);
+			assert_eq!(Cashflow::block_at(&Clock::Seconds, 1_030), 15);
+			assert_eq!(Cashflow::block_at(&Clock::Seconds, 1_031), 16);
+			// the past is reached by the current block
+			assert_eq!(Cashflow::block_at(&Clock::Seconds, 900), 10);
+
+			for block in 10..100 {
+				let time = Cashflow::time_at(&Clock::Seconds, block);
+				assert_eq!(Cashflow::block_at(&Clock::Seconds, time), block);
+			}
+		});
+	}
+
+	#[test]
+	fn month_clock_rolls_over_at_first_second() {
+		with_externalities(&mut new_test_ext(), || {
+			// noon of 2000-02-29
+			System::set_block_number(10);
+			Timestamp::set_timestamp(11_016 * SECS_PER_DAY + SECS_PER_DAY / 2);
+
+			assert_eq!(Cashflow::now(&Clock::Months), 361);
+			let midnight = 10 + SECS_PER_DAY / 2 / BLOCK_SECS;
+			assert_eq!(Cashflow::time_at(&Clock::Months, midnight - 1), 361);
+			assert_eq!(Cashflow::time_at(&Clock::Months, midnight), 362);
+			assert_eq!(Cashflow::block_at(&Clock::Months, 362), midnight);
+			assert_eq!(Cashflow::block_at(&Clock::Months, 361), 10);
+		});
+	}
+
+	#[test]
+	fn schedules_produce_installments() {
+		let constant: Schedule<u64, u64> = Schedule::Constant;
+		assert_eq!(constant.installment(10, 5, None, 100, 0), Some((105, 10)));
+		assert_eq!(constant.installment(10, 5, Some(112), 100, 2), Some((115, 10)));
+		assert_eq!(constant.installment(10, 5, Some(112), 100, 3), None);
+
+		let step_up: Schedule<u64, u64> = Schedule::StepUp(3);
+		assert_eq!(step_up.installment(10, 5, None, 0, 2), Some((15, 16)));
+		let step_down: Schedule<u64, u64> = Schedule::StepDown(4);
+		assert_eq!(step_down.installment(10, 5, None, 0, 3), Some((20, 0)));
+
+		let bullet: Schedule<u64, u64> = Schedule::Bullet;
+		assert_eq!(bullet.installment(10, 5, Some(50), 0, 0), Some((50, 10)));
+		assert_eq!(bullet.installment(10, 5, Some(50), 0, 1), None);
+
+		let list: Schedule<u64, u64> = Schedule::Installments(vec![(3, 7), (8, 9)]);
+		assert_eq!(list.installment(0, 0, None, 10, 1), Some((18, 9)));
+		assert_eq!(list.installment(0, 0, None, 10, 2), None);
+	}
+
+	#[test]
+	fn installments_are_validated() {
+		let unordered: Schedule<u64, u64> = Schedule::Installments(vec![(5, 1), (5, 1)]);
+		assert_eq!(unordered.validate(0, None, 10), Err(Error::UnorderedInstallments));
+		let empty: Schedule<u64, u64> = Schedule::Installments(vec![]);
+		assert_eq!(empty.validate(0, None, 10), Err(Error::NoInstallments));
+
+		let list: Schedule<u64, u64> = Schedule::Installments((1..=3).map(|k| (k, 1)).collect());
+		assert_eq!(list.validate(0, None, 3), Ok(()));
+		assert_eq!(list.validate(0, None, 2), Err(Error::TooManyInstallments));
+	}
+
+	#[test]
+	fn start_is_validated_against_schedule() {
+		let bullet: Schedule<u64, u64> = Schedule::Bullet;
+		let stream: Schedule<u64, u64> = Schedule::Stream;
+		let constant: Schedule<u64, u64> = Schedule::Constant;
+
+		assert_eq!(Start::Immediate.validate(&bullet), Ok(()));
+		assert_eq!(Start::Delay(5).validate(&bullet), Err(Error::StartNotApplicable));
+		assert_eq!(Start::FirstDue(5).validate(&bullet), Err(Error::StartNotApplicable));
+		assert_eq!(Start::Delay(5).validate(&stream), Ok(()));
+		assert_eq!(Start::FirstDue(5).validate(&stream), Err(Error::StartNotApplicable));
+		assert_eq!(Start::FirstDue(5).validate(&constant), Ok(()));
+
+		assert_eq!(Start::Delay(5).start_dt(&stream, 0, 10), Ok(15));
+		assert_eq!(Start::FirstDue(20).start_dt(&constant, 4, 10), Ok(16));
+		assert_eq!(Start::FirstDue(20).start_dt(&stream, 0, 10), Err(Error::StartNotApplicable));
+
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(
+				Cashflow::create_scheduled_promise(Origin::signed(ISSUER), 1, 0, 100, Schedule::Bullet, Start::Delay(10), Clock::Blocks),
+				Error::StartNotApplicable.as_str()
+			);
+			assert!(Cashflow::promises_of(&ISSUER).is_empty());
+		});
+	}
+
+	#[test]
+	fn genesis_reserves_deposits_without_events() {
+		let c2fc_id = H256::repeat_byte(1);
+		let promise_id = H256::repeat_byte(2);
+		let config = GenesisConfig::<Test> {
+			parameters: Parameters { storage_deposit: 10, ..Parameters::default() },
+			buckets: vec![(OWNER, c2fc_id, 0)],
+			promises: vec![(ISSUER, promise_id, 100, 5, 0, Schedule::Constant, Start::Immediate, Clock::Blocks)],
+			accepted: vec![(promise_id, c2fc_id)],
+			stakes: vec![(promise_id, 100)],
+		};
+
+		with_externalities(&mut new_test_ext_with(config), || {
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(OWNER));
+			assert_eq!(Cashflow::c2fc_by_promise(promise_id), c2fc_id);
+			assert_eq!(Cashflow::staked(promise_id).0, 100);
+			assert_eq!(Balances::reserved_balance(&OWNER), 10);
+			assert_eq!(Balances::reserved_balance(&ISSUER), 10);
+			assert!(System::events().is_empty());
+		});
+	}
+
+	#[test]
+	#[should_panic(expected = "Invalid c2fc genesis acception of the promise")]
+	fn genesis_rejects_own_promise() {
+		let c2fc_id = H256::repeat_byte(1);
+		let promise_id = H256::repeat_byte(2);
+		let config = GenesisConfig::<Test> {
+			buckets: vec![(ISSUER, c2fc_id, 0)],
+			promises: vec![(ISSUER, promise_id, 100, 5, 0, Schedule::Constant, Start::Immediate, Clock::Blocks)],
+			accepted: vec![(promise_id, c2fc_id)],
+			..GenesisConfig::default()
+		};
+		new_test_ext_with(config);
+	}
+
+	#[test]
+	fn legacy_storage_is_migrated_once() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = H256::repeat_byte(1);
+			let promise_id = H256::repeat_byte(2);
+			let free_id = H256::repeat_byte(3);
+			<StorageVersion<Test>>::put(0);
+
+			let old_promise = legacy::Promise { id: promise_id, owner: ISSUER, value: 100, period: 10, until: None, filled: 250, acception_dt: 3 };
+			<legacy::Buckets<Test>>::insert(c2fc_id, legacy::Bucket { id: c2fc_id, promise: Some(old_promise), price: 7 });
+			<legacy::BucketOwner<Test>>::insert(c2fc_id, OWNER);
+			<legacy::AllBucketsArray<Test>>::insert(0, c2fc_id);
+			<legacy::AllBucketsCount<Test>>::put(1);
+			<legacy::AllBucketsIndex<Test>>::insert(c2fc_id, 0);
+			<legacy::OwnedBucketsArray<Test>>::insert((OWNER, 0), c2fc_id);
+			<legacy::OwnedBucketsCount<Test>>::insert(OWNER, 1);
+			<legacy::OwnedBucketsIndex<Test>>::insert(c2fc_id, 0);
+			<legacy::Promises<Test>>::insert(free_id, legacy::FreePromise { id: free_id, value: 5, period: 20, until: Some(100) });
+			<FreePromisesArray<Test>>::insert(0, free_id);
+			<FreePromisesCount<Test>>::put(1);
+			// the free promise is staked in the native balance:
+			let lock_id = *b"legacy01";
+			<PromiseOwner<Test>>::insert(free_id, ISSUER);
+			<LockForPromise<Test>>::insert(free_id, lock_id);
+			<Balances as LockableCurrency<u64>>::set_lock(lock_id, &ISSUER, 40, 100, WithdrawReasons::from(WithdrawReason::Reserve));
+
+			System::set_block_number(25);
+			<Cashflow as OnInitialize<u64>>::on_initialize(25);
+			assert_eq!(Cashflow::storage_version(), STORAGE_VERSION);
+
+			let c2fc = Cashflow::c2fc(c2fc_id);
+			assert_eq!((c2fc.price, c2fc.price_asset, c2fc.creator, c2fc.royalty), (7, NATIVE_ASSET, OWNER, Permill::zero()));
+			let promise = c2fc.promise.unwrap();
+			assert_eq!((promise.start_dt, promise.installment, promise.filled), (3, 2, 50));
+			assert_eq!(promise.schedule, Schedule::Constant);
+
+			let free = Cashflow::promise(free_id);
+			assert_eq!((free.value, free.period, free.until, free.asset), (5, 20, Some(100), NATIVE_ASSET));
+			assert_eq!((free.start, free.clock), (Start::Immediate, Clock::Blocks));
+
+			// the native stake is unlocked and not staked in AKT:
+			assert!(Balances::locks(&ISSUER).is_empty());
+			assert!(!<LockForPromise<Test>>::exists(free_id));
+			assert_eq!(Cashflow::stake_of(free_id), None);
+			assert!(Akt::locks(&ISSUER).is_empty());
+			assert!(deposited(RawEvent::LegacyStakeUnlocked(free_id, ISSUER, 40)));
+
+			// the owners are moved to the registry:
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(OWNER));
+			assert_eq!(Cashflow::buckets_of(&OWNER), vec![c2fc_id]);
+			assert_eq!(<legacy::BucketOwner<Test>>::get(c2fc_id), None);
+			assert_eq!(<legacy::OwnedBucketsCount<Test>>::get(OWNER), 0);
+			assert_eq!(<legacy::AllBucketsCount<Test>>::get(), 0);
+
+			// the migrated storage isn't converted again:
+			<Buckets<Test>>::mutate(c2fc_id, |c2fc| c2fc.price = 8);
+			<Cashflow as OnInitialize<u64>>::on_initialize(26);
+			assert_eq!(Cashflow::c2fc(c2fc_id).price, 8);
+		});
+	}
+
+	#[test]
+	fn promise_installments_are_limited_by_parameters() {
+		with_externalities(&mut new_test_ext(), || {
+			let params = Parameters { max_installments: 2, ..Cashflow::parameters() };
+			assert_ok!(Cashflow::set_parameters(Origin::ROOT, params));
+
+			let schedule = Schedule::Installments(vec![(10, 1), (20, 1), (30, 1)]);
+			assert_err!(
+				Cashflow::create_scheduled_promise(Origin::signed(ISSUER), 1, 0, 0, schedule, Start::Immediate, Clock::Blocks),
+				Error::TooManyInstallments.as_str()
+			);
+			assert!(Cashflow::promises_of(&ISSUER).is_empty());
+
+			create_promise(1, 0, 0, Schedule::Installments(vec![(10, 1), (20, 1)]));
+			assert_eq!(Cashflow::promises_of(&ISSUER).len(), 1);
+		});
+	}
+
+	#[test]
+	fn creation_fee_is_charged_after_validation() {
+		with_externalities(&mut new_test_ext(), || {
+			let params = Parameters { creation_fee: 10, max_installments: 1, ..Cashflow::parameters() };
+			assert_ok!(Cashflow::set_parameters(Origin::ROOT, params));
+
+			let schedule = Schedule::Installments(vec![(10, 1), (20, 1)]);
+			assert_err!(
+				Cashflow::create_scheduled_promise(Origin::signed(ISSUER), 1, 0, 0, schedule, Start::Immediate, Clock::Blocks),
+				Error::TooManyInstallments.as_str()
+			);
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT);
+
+			create_promise(1, 0, 0, Schedule::Installments(vec![(10, 1)]));
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT - 10 - Cashflow::parameters().storage_deposit);
+		});
+	}
+
+	#[test]
+	fn fees_are_charged_in_assets_and_from_escrow() {
+		with_externalities(&mut new_test_ext(), || {
+			let fee = Permill::from_percent(10);
+			let params = Parameters { sale_fee: fee, payment_fee: fee, ..Cashflow::parameters() };
+			assert_ok!(Cashflow::set_parameters(Origin::ROOT, params));
+
+			// the sale in the issued asset requires its treasury:
+			assert_ok!(Token::issue(Origin::signed(OTHER), 1_000));
+			let c2fc_id = create_bucket();
+			assert_ok!(Cashflow::set_asset_price(Origin::signed(OWNER), c2fc_id, 1, 100));
+			assert_err!(Cashflow::buy_c2fc(Origin::signed(OTHER), c2fc_id, 100), Error::NoAssetTreasury.as_str());
+
+			let treasury = 4;
+			assert_err!(Cashflow::set_asset_treasury(Origin::ROOT, NATIVE_ASSET, treasury), Error::NativeAssetTreasury.as_str());
+			assert_ok!(Cashflow::set_asset_treasury(Origin::ROOT, 1, treasury));
+			assert_ok!(Cashflow::buy_c2fc(Origin::signed(OTHER), c2fc_id, 100));
+			assert_eq!(Token::free_balance_of(1, &OTHER), 900);
+			assert_eq!(Token::free_balance_of(1, &OWNER), 90);
+			assert_eq!(Token::free_balance_of(1, &treasury), 10);
+
+			// the installment paid from the escrow counts as filled in whole:
+			System::set_block_number(1);
+			let (_, promise_id) = accepted_promise(100, 5, 0, Schedule::Constant);
+			assert_ok!(Cashflow::deposit_escrow(Origin::signed(ISSUER), promise_id, 100));
+			run_to_block(6);
+			assert_eq!(Cashflow::accepted_promise(promise_id).unwrap().installment, 1);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 90);
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT - 100);
+			assert_eq!(Cashflow::escrow_of(promise_id), 0);
+		});
+	}
+
+	#[test]
+	fn royalty_is_limited_and_paid_with_the_price() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(
+				Cashflow::create_c2fc_with_royalty(Origin::signed(OWNER), Permill::from_percent(DEFAULT_MAX_ROYALTY + 1)),
+				Error::RoyaltyTooHigh.as_str()
+			);
+			assert_ok!(Cashflow::create_c2fc_with_royalty(Origin::signed(OWNER), Permill::from_percent(DEFAULT_MAX_ROYALTY)));
+			let c2fc_id = *Cashflow::buckets_of(&OWNER).last().expect("bucket is created");
+
+			// the creator sells without the royalty:
+			assert_ok!(Cashflow::set_price(Origin::signed(OWNER), c2fc_id, 100));
+			assert_ok!(Cashflow::buy_c2fc(Origin::signed(OTHER), c2fc_id, 100));
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 100);
+
+			// the buyer can't keep the account alive, neither the royalty nor the price is paid:
+			assert_ok!(Cashflow::set_price(Origin::signed(OTHER), c2fc_id, ENDOWMENT));
+			assert_err!(Cashflow::buy_c2fc(Origin::signed(ISSUER), c2fc_id, ENDOWMENT), Error::TransferFailed.as_str());
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 100);
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(OTHER));
+
+			assert_ok!(Cashflow::set_price(Origin::signed(OTHER), c2fc_id, 100));
+			assert_ok!(Cashflow::buy_c2fc(Origin::signed(ISSUER), c2fc_id, 100));
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT - 100);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 110);
+			assert_eq!(Balances::free_balance(&OTHER), ENDOWMENT - 10);
+		});
+	}
+
+	#[test]
+	fn payment_is_refused_if_recipient_can_not_receive_it() {
+		with_externalities(&mut new_test_ext(), || {
+			<balances::ExistentialDeposit<Test>>::put(50);
+			assert_ok!(Cashflow::create_c2fc_with_royalty(Origin::signed(OWNER), Permill::from_percent(DEFAULT_MAX_ROYALTY)));
+			let c2fc_id = *Cashflow::buckets_of(&OWNER).last().expect("bucket is created");
+
+			// the seller has no native balance, the price less the royalty can't create its account:
+			let seller = 4;
+			assert_ok!(Cashflow::transfer_from(Origin::signed(OWNER), OWNER, seller, c2fc_id));
+			assert_ok!(Cashflow::set_price(Origin::signed(seller), c2fc_id, 40));
+			assert_err!(Cashflow::buy_c2fc(Origin::signed(ISSUER), c2fc_id, 40), Error::TransferFailed.as_str());
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT);
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(seller));
+
+			assert_ok!(Cashflow::set_price(Origin::signed(seller), c2fc_id, 100));
+			assert_ok!(Cashflow::buy_c2fc(Origin::signed(ISSUER), c2fc_id, 100));
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT - 100);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 10);
+			assert_eq!(Balances::free_balance(&seller), 90);
+		});
+	}
+
+	#[test]
+	fn transfer_clears_approval_and_price() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Cashflow::set_approval_for_all(Origin::signed(OWNER), OWNER, true), Error::SelfApproval.as_str());
+
+			let c2fc_id = create_bucket();
+			assert_ok!(Cashflow::set_price(Origin::signed(OWNER), c2fc_id, 100));
+			assert_ok!(Cashflow::approve(Origin::signed(OWNER), c2fc_id, OTHER));
+			assert_eq!(Cashflow::approved_of_c2fc(c2fc_id), Some(OTHER));
+
+			assert_ok!(Cashflow::transfer_from(Origin::signed(OTHER), OWNER, ISSUER, c2fc_id));
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(ISSUER));
+			assert_eq!(Cashflow::approved_of_c2fc(c2fc_id), None);
+			assert_eq!(Cashflow::c2fc(c2fc_id).price, 0);
+
+			// neither the approved account nor the buyers can take it from the new owner:
+			assert_err!(Cashflow::transfer_from(Origin::signed(OTHER), ISSUER, OTHER, c2fc_id), Error::NotApproved.as_str());
+			assert_err!(Cashflow::buy_c2fc(Origin::signed(OWNER), c2fc_id, 100), Error::NotForSale.as_str());
+		});
+	}
+
+	#[test]
+	fn escrow_withdrawal_is_limited_by_reserved_balance() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = create_promise(100, 5, 0, Schedule::Constant);
+			assert_ok!(Cashflow::deposit_escrow(Origin::signed(ISSUER), promise_id, 100));
+			assert_eq!(Balances::reserved_balance(&ISSUER), 100);
+
+			// part of the reserved balance is taken by someone else:
+			let _ = <Balances as ReservableCurrency<u64>>::slash_reserved(&ISSUER, 40);
+
+			assert_ok!(Cashflow::withdraw_escrow(Origin::signed(ISSUER), promise_id, 100));
+			assert_eq!(Cashflow::escrow_of(promise_id), 40);
+			assert_eq!(Balances::reserved_balance(&ISSUER), 0);
+			assert_eq!(Balances::free_balance(&ISSUER), ENDOWMENT - 40);
+		});
+	}
+
+	#[test]
+	fn accrued_stream_stays_in_escrow() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(100, 0, 101, Schedule::Stream);
+			assert_ok!(Cashflow::deposit_escrow(Origin::signed(ISSUER), promise_id, 100));
+
+			run_to_block(51);
+			assert_err!(
+				Cashflow::withdraw_escrow(Origin::signed(ISSUER), promise_id, 51),
+				Error::InsufficientEscrow.as_str()
+			);
+			assert_ok!(Cashflow::withdraw_escrow(Origin::signed(ISSUER), promise_id, 50));
+			assert_eq!(Cashflow::escrow_of(promise_id), 50);
+
+			assert_ok!(Cashflow::claim_stream(Origin::signed(OWNER), c2fc_id));
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 50);
+			assert_eq!(Cashflow::escrow_of(promise_id), 0);
+		});
+	}
+
+	#[test]
+	fn stream_shortfall_slashes_stake() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(100, 0, 101, Schedule::Stream);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 100));
+
+			run_to_block(51);
+			assert_ok!(Cashflow::claim_stream(Origin::signed(OWNER), c2fc_id));
+
+			assert_eq!(Cashflow::stream_arrears((promise_id, OWNER)), 50);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 100 * SLASH_RATE as u64 / 1_000_000);
+		});
+	}
+
+	#[test]
+	fn arrears_stay_in_escrow_and_are_slashed_once() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(100, 0, 101, Schedule::Stream);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 100));
+
+			// the previous owner keeps the arrears accrued before the transfer:
+			run_to_block(51);
+			assert_ok!(Cashflow::transfer(Origin::signed(OWNER), OTHER, c2fc_id));
+			assert_eq!(Cashflow::stream_arrears((promise_id, OWNER)), 50);
+
+			// the shortfall is slashed only once until the arrears are paid:
+			run_to_block(61);
+			assert_ok!(Cashflow::claim_stream(Origin::signed(OTHER), c2fc_id));
+			assert_eq!(Cashflow::total_arrears(promise_id), 60);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 100 * SLASH_RATE as u64 / 1_000_000);
+
+			assert_ok!(Cashflow::deposit_escrow(Origin::signed(ISSUER), promise_id, 100));
+			assert_err!(
+				Cashflow::withdraw_escrow(Origin::signed(ISSUER), promise_id, 41),
+				Error::InsufficientEscrow.as_str()
+			);
+			assert_ok!(Cashflow::claim_arrears(Origin::signed(OWNER), promise_id));
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 50);
+			assert_eq!(Cashflow::total_arrears(promise_id), 10);
+		});
+	}
+
+	#[test]
+	fn stake_capacity_excludes_other_stakes_and_holds_delegations() {
+		with_externalities(&mut new_test_ext(), || {
+			let first = create_promise(100, 5, 20, Schedule::Constant);
+			let second = create_promise(100, 5, 20, Schedule::Constant);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), first, ENDOWMENT));
+			assert_err!(
+				Cashflow::stake_to_promise(Origin::signed(ISSUER), second, 1),
+				Error::InsufficientStake.as_str()
+			);
+
+			assert_ok!(Akt::bond(Origin::signed(OTHER), 500));
+			assert_ok!(Akt::delegate(Origin::signed(OTHER), ISSUER, 500));
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), second, 300));
+			assert_eq!(Cashflow::staked_delegated(second), 300);
+			assert_eq!(Akt::held_delegation(ISSUER), 300);
+			assert_err!(
+				Cashflow::stake_to_promise(Origin::signed(ISSUER), second, 201),
+				Error::InsufficientStake.as_str()
+			);
+
+			System::set_block_number(20);
+			assert_ok!(Cashflow::withdraw_staken(Origin::signed(ISSUER), second));
+			assert_eq!(Akt::held_delegation(ISSUER), 0);
+			assert_ok!(Cashflow::withdraw_staken(Origin::signed(ISSUER), first));
+			assert_eq!(Cashflow::issuer_staked(ISSUER), 0);
+		});
+	}
+
+	#[test]
+	fn staked_akt_can_not_be_burnt() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = create_promise(100, 5, 20, Schedule::Constant);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 100));
+
+			assert_err!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 99), "AKT are locked");
+			assert_ok!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 100));
+			assert_eq!(Akt::free_balance(&ISSUER), 100);
+		});
+	}
+
+	#[test]
+	fn bonded_and_staked_akt_are_not_counted_twice() {
+		with_externalities(&mut new_test_ext(), || {
+			let first = create_promise(100, 5, 20, Schedule::Constant);
+			let second = create_promise(100, 5, 20, Schedule::Constant);
+
+			// the bonded AKT can't be staked:
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), ENDOWMENT - 500));
+			assert_err!(
+				Cashflow::stake_to_promise(Origin::signed(ISSUER), first, 501),
+				Error::InsufficientStake.as_str()
+			);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), first, 300));
+
+			// the staked AKT can't be bonded:
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), ENDOWMENT));
+			assert_eq!(Akt::ledger(&ISSUER).unwrap().total, ENDOWMENT - 300);
+			assert_err!(Akt::bond(Origin::signed(ISSUER), 1), "Nothing to bond");
+			assert_err!(
+				Cashflow::stake_to_promise(Origin::signed(ISSUER), second, 1),
+				Error::InsufficientStake.as_str()
+			);
+		});
+	}
+
+	#[test]
+	fn breach_slashes_held_delegations() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(Akt::bond(Origin::signed(OTHER), 500));
+			assert_ok!(Akt::delegate(Origin::signed(OTHER), ISSUER, 500));
+			assert_ok!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 100));
+
+			let (_, promise_id) = accepted_promise(100, 5, 0, Schedule::Constant);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 300));
+
+			run_to_block(6);
+			let own_slash = 100 * SLASH_RATE as u64 / 1_000_000;
+			let delegated_slash = 200 * SLASH_RATE as u64 / 1_000_000;
+			assert_eq!(Akt::free_balance(&ISSUER), 100 - own_slash);
+			assert_eq!(Akt::free_balance(&OTHER), ENDOWMENT - delegated_slash);
+
+			// the slashed AKT are removed from the stake and the held delegations:
+			assert_eq!(Cashflow::staked(promise_id).0, 300 - own_slash - delegated_slash);
+			assert_eq!(Cashflow::staked_delegated(promise_id), 200 - delegated_slash);
+			assert_eq!(Cashflow::issuer_staked(ISSUER), 100 - own_slash);
+			assert_eq!(Akt::held_delegation(ISSUER), 200 - delegated_slash);
+			assert_eq!(Akt::total_delegated(ISSUER), 500 - delegated_slash);
+		});
+	}
+
+	#[test]
+	fn zero_deposit_is_rejected() {
+		with_externalities(&mut new_test_ext(), || {
+			let (c2fc_id, promise_id) = accepted_promise(100, 5, 0, Schedule::Constant);
+
+			assert_err!(Cashflow::fill_c2fc(Origin::signed(ISSUER), c2fc_id, 0), Error::ZeroDeposit.as_str());
+			assert_eq!(Cashflow::accepted_promise(promise_id).unwrap().filled, 0);
+		});
+	}
+
+	#[test]
+	fn unpaid_installment_is_breached() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(100, 5, 0, Schedule::Constant);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 100));
+
+			run_to_block(5);
+			assert_ok!(Cashflow::fill_c2fc(Origin::signed(ISSUER), c2fc_id, 100));
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 100);
+
+			run_to_block(10);
+			assert!(!deposited(RawEvent::PromiseBreached(c2fc_id, promise_id, 100)));
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT);
+
+			run_to_block(11);
+			let promise = Cashflow::accepted_promise(promise_id).unwrap();
+			assert_eq!(promise.installment, 2);
+			assert_eq!(promise.filled, 0);
+			assert!(deposited(RawEvent::PromiseBreached(c2fc_id, promise_id, 100)));
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 100 * SLASH_RATE as u64 / 1_000_000);
+		});
 	}
 
 	#[test]
-	fn it_works_for_default_value() {
+	fn grace_period_delays_only_breach() {
 		with_externalities(&mut new_test_ext(), || {
-			// Just a dummy test for the dummy funtion `do_something`
-			// calling the `do_something` function with a value 42
-			assert_ok!(CashflowModule::do_something(Origin::signed(1), 42));
-			// asserting that the stored value is equal to what we stored
-			assert_eq!(CashflowModule::something(), Some(42));
+			let params = Parameters { grace_period: 3, ..Cashflow::parameters() };
+			assert_ok!(Cashflow::set_parameters(Origin::ROOT, params));
+			System::set_block_number(1);
+			let (_, promise_id) = accepted_promise(100, 5, 0, Schedule::Constant);
+			assert_ok!(Cashflow::stake_to_promise(Origin::signed(ISSUER), promise_id, 100));
+			assert_ok!(Cashflow::deposit_escrow(Origin::signed(ISSUER), promise_id, 100));
+
+			// the first installment is paid from the escrow right when it's due:
+			run_to_block(6);
+			assert_eq!(Cashflow::accepted_promise(promise_id).unwrap().installment, 1);
+			assert_eq!(Balances::free_balance(&OWNER), ENDOWMENT + 100);
+			assert_eq!(Cashflow::escrow_of(promise_id), 0);
+
+			// the second one is unpaid but still in the grace period:
+			run_to_block(13);
+			assert_eq!(Cashflow::accepted_promise(promise_id).unwrap().installment, 1);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT);
+
+			run_to_block(14);
+			assert_eq!(Cashflow::accepted_promise(promise_id).unwrap().installment, 2);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 100 * SLASH_RATE as u64 / 1_000_000);
 		});
 	}
 }
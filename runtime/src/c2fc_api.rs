@@ -0,0 +1,41 @@
+//! Runtime API to query C2FC buckets, promises and portfolios.
+
+use rstd::prelude::*;
+use parity_codec::Codec;
+use client::decl_runtime_apis;
+
+use crate::c2fc::{Bucket, FreePromise, Promise, PromiseFilter, RawEvent};
+
+
+decl_runtime_apis! {
+	/// The API to query the `Cashflow` module.
+	///
+	/// The AKT balance of stake events is the same type as `Balance`.
+	#[api_version(1)]
+	pub trait C2fcApi<AccountId, Hash, Balance, BlockNumber> where
+		AccountId: Codec,
+		Hash: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Returns the bucket with specified id.
+		fn bucket(id: Hash) -> Option<Bucket<Hash, Balance, AccountId, BlockNumber>>;
+		/// Returns the promise with specified id in the state it was created.
+		fn promise(id: Hash) -> Option<FreePromise<Hash, Balance, BlockNumber>>;
+		/// Returns the promise with specified id if it is accepted to a bucket.
+		fn accepted_promise(id: Hash) -> Option<Promise<Hash, Balance, AccountId, BlockNumber>>;
+		/// Ids of all buckets owned by the account.
+		fn buckets_of(who: AccountId) -> Vec<Hash>;
+		/// Ids of all promises issued by the account.
+		fn promises_of(who: AccountId) -> Vec<Hash>;
+		/// Amount which should be filled to the promise to avoid any breach up to block `at`.
+		fn due_amount(promise_id: Hash, at: BlockNumber) -> Balance;
+		/// Ids of promises matching the filter, `PROMISES_PAGE_SIZE` per page.
+		fn free_promises(page: u32, filter: PromiseFilter<AccountId, Balance>) -> Vec<Hash>;
+		/// Events of the `Cashflow` module deposited in the block.
+		#[skip_initialize_block]
+		fn events() -> Vec<RawEvent<AccountId, Hash, Balance, Balance>>;
+		/// Index and message of every error reported by `Failed` event.
+		fn errors() -> Vec<(u8, Vec<u8>)>;
+	}
+}
@@ -73,6 +73,16 @@ pub type Balance = u128;
 
 mod c2fc;
 mod stake;
+mod token;
+mod nft;
+pub mod c2fc_api;
+pub mod stake_api;
+
+pub use c2fc::{Bucket, Promise, FreePromise, PromiseFilter, Parameters, Schedule, Start, Clock, PROMISES_PAGE_SIZE};
+pub use c2fc::RawEvent as C2fcEvent;
+pub use c2fc::Error as C2fcError;
+pub use token::{AssetId, NATIVE_ASSET};
+pub use stake::{StakingLedger, UnlockChunk, IssuanceSchedule, ScheduleId, EraIndex, RATE_DENOMINATOR};
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -105,16 +115,14 @@ pub mod opaque {
 	// pub type SessionKey = AuthorityId;
 	pub type SessionKey = AuraId;
 
-	// / Opaque AssetId key type.
-	// pub type AssetId = u32; // assets::AssetId;
 }
 
 /// This runtime version.
 pub const VERSION: RuntimeVersion = RuntimeVersion { spec_name: create_runtime_str!("akropolis"),
                                                      impl_name: create_runtime_str!("akropolis"),
                                                      authoring_version: 3,
-                                                     spec_version: 4,
-                                                     impl_version: 4,
+                                                     spec_version: 6,
+                                                     impl_version: 6,
                                                      apis: RUNTIME_API_VERSIONS };
 
 /// The version infromation used to identify this runtime when compiled natively.
@@ -195,16 +203,11 @@ impl balances::Trait for Runtime {
 	type Event = Event;
 
 	type TransactionPayment = ();
-	type DustRemoval = ();
+	/// Dust of the reaped accounts goes to the treasury.
+	type DustRemoval = Treasury;
 	type TransferPayment = ();
 }
 
-// impl assets::Trait for Runtime {
-// 	type Event = Event;
-// 	// type Balance = Balance;
-// 	type Balance = <Runtime as balances::Trait>::Balance;
-// }
-
 impl session::Trait for Runtime {
 	type ConvertAccountIdToSessionKey = ();
 	type OnSessionChange = (Staking, grandpa::SyncedAuthorities<Runtime>);
@@ -218,10 +221,22 @@ impl staking::Trait for Runtime {
 	type CurrencyToVote = ();
 	type OnRewardMinted = ();
 	type Event = Event;
-	type Slash = ();
+	/// Slashed validators pay to the treasury.
+	type Slash = Treasury;
 	type Reward = ();
 }
 
+impl treasury::Trait for Runtime {
+	type Currency = Balances;
+	/// Proposals are approved and rejected by the root until there is a council.
+	type ApproveOrigin = system::EnsureRoot<AccountId>;
+	type RejectOrigin = system::EnsureRoot<AccountId>;
+	/// The ubiquitous event type.
+	type Event = Event;
+	type MintedForSpending = ();
+	type ProposalRejection = ();
+}
+
 
 impl grandpa::Trait for Runtime {
 	type SessionKey = AuthorityId;
@@ -244,25 +259,33 @@ impl sudo::Trait for Runtime {
 
 
 impl c2fc::Trait for Runtime {
-	/// Currency for staking
-	// TODO: change to Currency in stake.rs mod.
-	// type Stake = Stake;
-	type Stake = Balances;
+	/// AKT locked as collateral of promises
+	type Stake = Stake;
+	/// AKT rewards for the staked promises paid on time
+	type OnPeriodPaid = Stake;
+	/// AKT delegated to the issuers, slashed on breach
+	type Backing = Stake;
+	/// Protocol fees of the market
+	type ProtocolFee = Treasury;
 	/// The ubiquitous event type.
 	type Event = Event;
 }
 
 impl stake::Trait for Runtime {
+	/// AKT balance
+	type Balance = Balance;
+	/// The own stakes of the promises can't be bonded.
+	type Collateral = Cashflow;
+	/// The ubiquitous event type.
+	type Event = Event;
+}
+
+impl token::Trait for Runtime {
 	/// The ubiquitous event type.
 	type Event = Event;
 }
 
-// impl token::Trait for Runtime {
-// 	/// The ubiquitous event type.
-// 	type Event = Event;
-// 	type Balance = <Runtime as balances::Trait>::Balance;
-// 	type AssetId = u32;
-// }
+impl nft::Trait for Runtime {}
 
 
 construct_runtime!(
@@ -278,18 +301,18 @@ construct_runtime!(
 		Consensus: consensus::{Module, Call, Storage, Config<T>, Log(AuthoritiesChange), Inherent},
 		Indices: indices,
 		Balances: balances,
-		// Assets: assets::{Module, Event<T>, AssetId},
-		// Assets: assets::{Module, Call, Storage, Event<T>, AssetId},
-		// TODO: Treasury: treasury,
 		Session: session,
 		Staking: staking::{default, OfflineWorker},
 		FinalityTracker: finality_tracker::{Module, Call, Inherent},
 		Grandpa: grandpa::{Module, Call, Storage, Config<T>, Log(), Event<T>},
 		Sudo: sudo,
 		// C2FC:
-		Cashflow: c2fc::{Module, Call, Storage, Event<T>, Bucket},
-		Stake: stake::{Module, Call, Storage, Event<T>},
-		// Token: token::{Module, Call, Storage, Event<T>},
+		Cashflow: c2fc::{Module, Call, Storage, Config<T>, Event<T>, Bucket},
+		Stake: stake::{Module, Call, Storage, Config<T>, Event<T>},
+		// new modules go to the end to keep the indices of the calls and events:
+		Token: token::{Module, Call, Storage, Event<T>},
+		Nft: nft::{Module, Storage},
+		Treasury: treasury,
 	}
 );
 
@@ -415,4 +438,84 @@ impl_runtime_apis! {
 		}
 	}
 
+
+	impl c2fc_api::C2fcApi<Block, AccountId, Hash, Balance, BlockNumber> for Runtime {
+		fn bucket(id: Hash) -> Option<Bucket<Hash, Balance, AccountId, BlockNumber>> {
+			Cashflow::bucket(id)
+		}
+
+		fn promise(id: Hash) -> Option<FreePromise<Hash, Balance, BlockNumber>> {
+			Cashflow::free_promise(id)
+		}
+
+		fn accepted_promise(id: Hash) -> Option<Promise<Hash, Balance, AccountId, BlockNumber>> {
+			Cashflow::accepted_promise(id)
+		}
+
+		fn buckets_of(who: AccountId) -> Vec<Hash> {
+			Cashflow::buckets_of(&who)
+		}
+
+		fn promises_of(who: AccountId) -> Vec<Hash> {
+			Cashflow::promises_of(&who)
+		}
+
+		fn due_amount(promise_id: Hash, at: BlockNumber) -> Balance {
+			Cashflow::due_amount(promise_id, at)
+		}
+
+		fn free_promises(page: u32, filter: PromiseFilter<AccountId, Balance>) -> Vec<Hash> {
+			Cashflow::free_promises(page, filter)
+		}
+
+		fn events() -> Vec<C2fcEvent<AccountId, Hash, Balance, Balance>> {
+			System::events().into_iter()
+				.filter_map(|record| match record.event {
+					Event::c2fc(event) => Some(event),
+					_ => None,
+				})
+				.collect()
+		}
+
+		fn errors() -> Vec<(u8, Vec<u8>)> {
+			C2fcError::ALL.iter().map(|e| (*e as u8, e.as_str().as_bytes().to_vec())).collect()
+		}
+	}
+
+	impl stake_api::StakeApi<Block, AccountId, Balance, BlockNumber> for Runtime {
+		fn ledger(who: AccountId) -> Option<StakingLedger<Balance, BlockNumber>> {
+			Stake::ledger(&who)
+		}
+
+		fn unbonding_duration() -> BlockNumber {
+			Stake::unbonding_duration()
+		}
+
+		fn issuance() -> (Balance, Balance, Balance) {
+			(Stake::total_issuance(), Stake::scheduled_issuance(), Stake::max_issuance())
+		}
+
+		fn schedule(id: ScheduleId) -> Option<IssuanceSchedule<AccountId, Balance, BlockNumber>> {
+			Stake::schedule(id)
+		}
+
+		fn delegations_of(delegator: AccountId) -> Vec<(AccountId, Balance)> {
+			Stake::delegated_to(&delegator).into_iter()
+				.map(|issuer| {
+					let amount = Stake::delegation((delegator.clone(), issuer.clone()));
+					(issuer, amount)
+				})
+				.collect()
+		}
+
+		fn delegators_of(issuer: AccountId) -> Vec<(AccountId, Balance)> {
+			Stake::delegators_of(&issuer).into_iter()
+				.map(|delegator| {
+					let amount = Stake::delegation((delegator.clone(), issuer.clone()));
+					(delegator, amount)
+				})
+				.collect()
+		}
+	}
+
 }
@@ -0,0 +1,347 @@
+//! Registry of non-fungible tokens owned by accounts.
+//!
+//! Tokens are grouped into collections, so any module can keep its own tokens in the registry,
+//! e.g. c2fc keeps the buckets as `c2fc::BucketTokens`.
+//! All the tokens of a collection and the tokens of every owner are enumerable by index,
+//! removal is done with "swap and pop" so the indices stay dense.
+
+use rstd::marker::PhantomData;
+use support::StorageMap;
+use support::dispatch::Result;
+use support::{decl_module, decl_storage};
+use support::ensure;
+
+
+/// Id of the collection of tokens.
+pub type CollectionId = [u8; 8];
+
+/// Non-fungible tokens owned by accounts.
+pub trait NonFungible<AccountId> {
+	/// Id of the token.
+	type TokenId;
+
+	/// Owner of the token, `None` if there is no such token.
+	fn owner_of(id: &Self::TokenId) -> Option<AccountId>;
+	/// Number of all the tokens.
+	fn total() -> u64;
+	/// Token by its index among all the tokens.
+	fn token_by_index(index: u64) -> Self::TokenId;
+	/// Number of the tokens owned by the account.
+	fn balance_of(owner: &AccountId) -> u64;
+	/// Token by its index among the tokens of the owner.
+	fn token_of_owner_by_index(owner: &AccountId, index: u64) -> Self::TokenId;
+
+	/// Create a new token owned by `to`.
+	fn mint(to: &AccountId, id: Self::TokenId) -> Result;
+	/// Move the token of `from` to `to`.
+	fn transfer(from: &AccountId, to: &AccountId, id: Self::TokenId) -> Result;
+	/// Delete the token.
+	fn burn(id: Self::TokenId) -> Result;
+}
+
+/// Id of the collection kept by a module.
+pub trait Collected {
+	const COLLECTION: CollectionId;
+}
+
+/// Tokens of the collection `C` as `NonFungible`.
+pub struct Collection<T, C>(PhantomData<(T, C)>);
+
+
+pub trait Trait: system::Trait {}
+
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Nft {
+		/// (collection, token_id) -> owner
+		TokenOwner get(owner_of): map (CollectionId, T::Hash) => Option<T::AccountId>;
+
+		AllTokensArray get(token_by_index): map (CollectionId, u64) => T::Hash;
+		AllTokensCount get(total_of): map CollectionId => u64;
+		AllTokensIndex: map (CollectionId, T::Hash) => u64;
+
+		OwnedTokensArray get(token_of_owner_by_index): map (CollectionId, T::AccountId, u64) => T::Hash;
+		OwnedTokensCount get(balance_of): map (CollectionId, T::AccountId) => u64;
+		OwnedTokensIndex: map (CollectionId, T::Hash) => u64;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+}
+
+
+impl<T: Trait> Module<T> {
+	/// Create the token of the collection owned by `to`.
+	pub fn mint(collection: CollectionId, to: &T::AccountId, id: T::Hash) -> Result {
+		ensure!(!<TokenOwner<T>>::exists((collection, id)), "This token already exists");
+
+		let total = Self::total_of(collection);
+		let new_total = total.checked_add(1).ok_or("Overflow adding a new token")?;
+		let balance = Self::balance_of((collection, to.clone()));
+		balance.checked_add(1).ok_or("Overflow adding a new token to the account")?;
+
+		<TokenOwner<T>>::insert((collection, id), to);
+
+		<AllTokensArray<T>>::insert((collection, total), id);
+		<AllTokensCount<T>>::insert(collection, new_total);
+		<AllTokensIndex<T>>::insert((collection, id), total);
+
+		Self::push_owned(collection, to, id);
+
+		Ok(())
+	}
+
+	/// Move the token of the collection from `from` to `to`.
+	pub fn transfer(collection: CollectionId, from: &T::AccountId, to: &T::AccountId, id: T::Hash) -> Result {
+		let owner = Self::owner_of((collection, id)).ok_or("This token does not exist")?;
+		ensure!(owner == *from, "You do not own this token");
+		if from == to {
+			return Ok(());
+		}
+		Self::balance_of((collection, to.clone())).checked_add(1).ok_or("Overflow adding a new token to the account")?;
+
+		Self::pop_owned(collection, from, id);
+		Self::push_owned(collection, to, id);
+		<TokenOwner<T>>::insert((collection, id), to);
+
+		Ok(())
+	}
+
+	/// Delete the token of the collection.
+	pub fn burn(collection: CollectionId, id: T::Hash) -> Result {
+		let owner = Self::owner_of((collection, id)).ok_or("This token does not exist")?;
+
+		// "Swap and pop" from all the tokens
+		let index = <AllTokensIndex<T>>::take((collection, id));
+		let last = Self::total_of(collection).saturating_sub(1);
+		if index != last {
+			let last_id = <AllTokensArray<T>>::get((collection, last));
+			<AllTokensArray<T>>::insert((collection, index), last_id);
+			<AllTokensIndex<T>>::insert((collection, last_id), index);
+		}
+		<AllTokensArray<T>>::remove((collection, last));
+		<AllTokensCount<T>>::insert(collection, last);
+
+		Self::pop_owned(collection, &owner, id);
+		<TokenOwner<T>>::remove((collection, id));
+
+		Ok(())
+	}
+
+	/// Append the token to the tokens of the owner.
+	fn push_owned(collection: CollectionId, owner: &T::AccountId, id: T::Hash) {
+		let balance = Self::balance_of((collection, owner.clone()));
+		<OwnedTokensArray<T>>::insert((collection, owner.clone(), balance), id);
+		<OwnedTokensCount<T>>::insert((collection, owner.clone()), balance + 1);
+		<OwnedTokensIndex<T>>::insert((collection, id), balance);
+	}
+
+	/// "Swap and pop" the token from the tokens of the owner.
+	fn pop_owned(collection: CollectionId, owner: &T::AccountId, id: T::Hash) {
+		let index = <OwnedTokensIndex<T>>::take((collection, id));
+		let last = Self::balance_of((collection, owner.clone())).saturating_sub(1);
+		if index != last {
+			let last_id = <OwnedTokensArray<T>>::get((collection, owner.clone(), last));
+			<OwnedTokensArray<T>>::insert((collection, owner.clone(), index), last_id);
+			<OwnedTokensIndex<T>>::insert((collection, last_id), index);
+		}
+		<OwnedTokensArray<T>>::remove((collection, owner.clone(), last));
+		<OwnedTokensCount<T>>::insert((collection, owner.clone()), last);
+	}
+}
+
+impl<T: Trait, C: Collected> NonFungible<T::AccountId> for Collection<T, C> {
+	type TokenId = T::Hash;
+
+	fn owner_of(id: &T::Hash) -> Option<T::AccountId> {
+		<Module<T>>::owner_of((C::COLLECTION, *id))
+	}
+
+	fn total() -> u64 {
+		<Module<T>>::total_of(C::COLLECTION)
+	}
+
+	fn token_by_index(index: u64) -> T::Hash {
+		<Module<T>>::token_by_index((C::COLLECTION, index))
+	}
+
+	fn balance_of(owner: &T::AccountId) -> u64 {
+		<Module<T>>::balance_of((C::COLLECTION, owner.clone()))
+	}
+
+	fn token_of_owner_by_index(owner: &T::AccountId, index: u64) -> T::Hash {
+		<Module<T>>::token_of_owner_by_index((C::COLLECTION, owner.clone(), index))
+	}
+
+	fn mint(to: &T::AccountId, id: T::Hash) -> Result {
+		<Module<T>>::mint(C::COLLECTION, to, id)
+	}
+
+	fn transfer(from: &T::AccountId, to: &T::AccountId, id: T::Hash) -> Result {
+		<Module<T>>::transfer(C::COLLECTION, from, to, id)
+	}
+
+	fn burn(id: T::Hash) -> Result {
+		<Module<T>>::burn(C::COLLECTION, id)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok, assert_noop};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup},
+		testing::{Digest, DigestItem, Header}
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl Trait for Test {}
+
+	struct Cards;
+	impl Collected for Cards {
+		const COLLECTION: CollectionId = *b"testcard";
+	}
+	struct Shares;
+	impl Collected for Shares {
+		const COLLECTION: CollectionId = *b"testshar";
+	}
+
+	type Nft = Module<Test>;
+	type CardsOf = Collection<Test, Cards>;
+	type SharesOf = Collection<Test, Shares>;
+
+	const ACCOUNTS: [u64; 3] = [1, 2, 3];
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		system::GenesisConfig::<Test>::default()
+			.build_storage()
+			.unwrap()
+			.0
+			.into()
+	}
+
+	fn token(n: u8) -> H256 {
+		H256::repeat_byte(n)
+	}
+
+	/// Owner, index and count maps of the collection agree with each other.
+	fn assert_consistent<C: Collected>() {
+		let collection = C::COLLECTION;
+		let total = Nft::total_of(collection);
+
+		for index in 0..total {
+			let id = Nft::token_by_index((collection, index));
+			assert_eq!(<AllTokensIndex<Test>>::get((collection, id)), index);
+
+			let owner = Nft::owner_of((collection, id)).expect("enumerated token has an owner");
+			let owned_index = <OwnedTokensIndex<Test>>::get((collection, id));
+			assert!(owned_index < Nft::balance_of((collection, owner)));
+			assert_eq!(Nft::token_of_owner_by_index((collection, owner, owned_index)), id);
+		}
+		assert!(!<AllTokensArray<Test>>::exists((collection, total)));
+
+		let mut owned = 0;
+		for &owner in ACCOUNTS.iter() {
+			let balance = Nft::balance_of((collection, owner));
+			for index in 0..balance {
+				let id = Nft::token_of_owner_by_index((collection, owner, index));
+				assert_eq!(Nft::owner_of((collection, id)), Some(owner));
+				assert_eq!(<OwnedTokensIndex<Test>>::get((collection, id)), index);
+			}
+			assert!(!<OwnedTokensArray<Test>>::exists((collection, owner, balance)));
+			owned += balance;
+		}
+		assert_eq!(owned, total);
+	}
+
+	#[test]
+	fn mint_transfer_burn_keep_maps_consistent() {
+		with_externalities(&mut new_test_ext(), || {
+			for n in 0..6 {
+				assert_ok!(CardsOf::mint(&ACCOUNTS[n as usize % 3], token(n)));
+				assert_consistent::<Cards>();
+			}
+			assert_eq!(CardsOf::total(), 6);
+			assert_eq!(CardsOf::balance_of(&1), 2);
+
+			assert_ok!(CardsOf::transfer(&1, &2, token(0)));
+			assert_consistent::<Cards>();
+			assert_ok!(CardsOf::transfer(&2, &2, token(0)));
+			assert_consistent::<Cards>();
+			assert_ok!(CardsOf::transfer(&3, &1, token(5)));
+			assert_consistent::<Cards>();
+			assert_eq!(CardsOf::owner_of(&token(0)), Some(2));
+			assert_eq!(CardsOf::balance_of(&2), 3);
+
+			for &n in [4, 0, 5, 1].iter() {
+				assert_ok!(CardsOf::burn(token(n)));
+				assert_consistent::<Cards>();
+			}
+			assert_eq!(CardsOf::total(), 2);
+			assert_eq!(CardsOf::owner_of(&token(0)), None);
+
+			for &n in [2, 3].iter() {
+				assert_ok!(CardsOf::burn(token(n)));
+				assert_consistent::<Cards>();
+			}
+			assert_eq!(CardsOf::total(), 0);
+		});
+	}
+
+	#[test]
+	fn invalid_changes_are_rejected() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CardsOf::mint(&1, token(1)));
+
+			assert_noop!(CardsOf::mint(&2, token(1)), "This token already exists");
+			assert_noop!(CardsOf::transfer(&2, &3, token(1)), "You do not own this token");
+			assert_noop!(CardsOf::transfer(&1, &3, token(2)), "This token does not exist");
+			assert_noop!(CardsOf::burn(token(2)), "This token does not exist");
+			assert_consistent::<Cards>();
+		});
+	}
+
+	#[test]
+	fn collections_are_separate() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CardsOf::mint(&1, token(1)));
+			assert_ok!(SharesOf::mint(&2, token(1)));
+			assert_ok!(SharesOf::mint(&1, token(2)));
+
+			assert_eq!(CardsOf::owner_of(&token(1)), Some(1));
+			assert_eq!(SharesOf::owner_of(&token(1)), Some(2));
+			assert_eq!(CardsOf::total(), 1);
+			assert_eq!(SharesOf::balance_of(&1), 1);
+
+			assert_ok!(SharesOf::burn(token(1)));
+			assert_eq!(CardsOf::owner_of(&token(1)), Some(1));
+			assert_consistent::<Cards>();
+			assert_consistent::<Shares>();
+		});
+	}
+}
@@ -1,31 +1,223 @@
-// use crate::Balances;
-// use support::StorageMap;
-// use support::StorageValue;
-// use support::dispatch::Result;
+//! AKT: the staking currency, a balance separate from the native one.
+//!
+//! The module implements `Currency` and `LockableCurrency` for the AKT balance,
+//! so other modules (e.g. C2FC collateral) can lock AKT the same way as the native balance.
+
+use rstd::prelude::*;
+use rstd::{cmp, result};
+use support::{StorageMap, StorageValue, Parameter};
+use support::dispatch::Result;
 use support::{decl_module, decl_storage, decl_event};
-// use support::{ensure, fail};
-// use system::{ensure_signed, ensure_root};
-// use runtime_primitives::traits::{Hash, Zero};
+use support::ensure;
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::traits::{As, Bounded, Member, MaybeSerializeDebug, Saturating, SimpleArithmetic, Zero};
+use balances::BalanceLock;
+
+#[cfg(feature = "std")]
+use runtime_io::with_storage;
+
+use crate::c2fc::{Backing, OnPeriodPaid};
 // use assets::*;
 
-// #[cfg(feature = "std")]
-// use serde::{Serialize, Deserialize};
-// use parity_codec::{Encode, Decode};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use parity_codec::{Encode, Decode};
 
-// use support::traits::{Currency, ReservableCurrency, OnDilution, OnUnbalanced, Imbalance};
+use support::traits::{Currency, LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons};
+use support::traits::{ExistenceRequirement, Imbalance, SignedImbalance, UpdateBalanceOutcome};
+
+pub use self::imbalances::{PositiveImbalance, NegativeImbalance};
 // use runtime_io::print;
 
 
-// pub trait Trait: balances::Trait where Self: Currency<Self::AccountId> {
-// pub trait Trait: balances::Trait where Self: Currency<<Self as system::Trait>::AccountId> {
-pub trait Trait: balances::Trait {
+/// Lock of the bonded AKT.
+const STAKE_ID: LockIdentifier = *b"akrstake";
+
+/// Max number of unlocking chunks of the ledger.
+pub const MAX_UNLOCKING_CHUNKS: usize = 32;
+
+/// Max number of issuers an account can delegate to.
+pub const MAX_DELEGATIONS: usize = 16;
+
+/// Max number of delegators of the issuer.
+pub const MAX_DELEGATORS: usize = 64;
+
+/// Max number of beneficiaries of the issuance schedule.
+pub const MAX_BENEFICIARIES: usize = 64;
+
+/// Id of the issuance schedule.
+pub type ScheduleId = u16;
+
+/// Index of the reward era.
+pub type EraIndex = u32;
+
+/// Denominator of the reward rate.
+pub const RATE_DENOMINATOR: u32 = 1_000_000;
+
+
+/// Value unbonded at some point, which can be withdrawn at `until`.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct UnlockChunk<Balance, BlockNumber> {
+	/// amount of funds to be unlocked
+	pub value: Balance,
+	/// block at which the funds can be withdrawn
+	pub until: BlockNumber,
+}
+
+/// Bonded AKT of the account.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct StakingLedger<Balance, BlockNumber> {
+	/// total amount locked, including the unlocking chunks
+	pub total: Balance,
+	/// amount which is bonded and not being unlocked
+	pub active: Balance,
+	/// part of the active amount delegated to issuers
+	pub delegated: Balance,
+	/// amounts unbonded and waiting for the unbonding period
+	pub unlocking: Vec<UnlockChunk<Balance, BlockNumber>>,
+}
+
+impl<Balance, BlockNumber> StakingLedger<Balance, BlockNumber>
+	where Balance: Copy + Saturating + Zero,
+	      BlockNumber: Copy + PartialOrd,
+{
+	/// Remove the chunks unlocked by `now`.
+	fn consolidate_unlocked(self, now: BlockNumber) -> Self {
+		let mut total = self.total;
+		let unlocking = self.unlocking.into_iter().filter(|chunk| if chunk.until > now {
+			true
+		} else {
+			total = total.saturating_sub(chunk.value);
+			false
+		}).collect();
+		Self { total, active: self.active, delegated: self.delegated, unlocking }
+	}
+}
+
+
+/// AKT released linearly to the beneficiaries from `start` during `duration` blocks.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct IssuanceSchedule<AccountId, Balance, BlockNumber> {
+	/// (beneficiary, total amount, released amount)
+	pub beneficiaries: Vec<(AccountId, Balance, Balance)>,
+	pub start: BlockNumber,
+	pub duration: BlockNumber,
+}
+
+impl<AccountId, Balance, BlockNumber> IssuanceSchedule<AccountId, Balance, BlockNumber>
+	where Balance: SimpleArithmetic + Copy,
+	      BlockNumber: SimpleArithmetic + Copy,
+{
+	/// Part of `total` released by `now`.
+	pub fn vested(&self, total: Balance, now: BlockNumber) -> Balance {
+		if now <= self.start {
+			Zero::zero()
+		} else if now >= self.start.saturating_add(self.duration) {
+			total
+		} else {
+			let elapsed = Balance::sa((now - self.start).as_());
+			let duration = Balance::sa(self.duration.as_());
+			// split to avoid the overflow of `total * elapsed`:
+			total / duration * elapsed + total % duration * elapsed / duration
+		}
+	}
+}
+
+
+/// AKT used as collateral by other modules, e.g. the own stakes of C2FC promises.
+pub trait Collateral<AccountId, Balance> {
+	/// AKT of the account staked outside of the module, they can't be bonded.
+	fn staked(who: &AccountId) -> Balance;
+}
+
+impl<AccountId, Balance: Zero> Collateral<AccountId, Balance> for () {
+	fn staked(_: &AccountId) -> Balance {
+		Zero::zero()
+	}
+}
+
+
+pub trait Trait: system::Trait {
+	/// The AKT balance.
+	type Balance: Parameter + Member + SimpleArithmetic + Default + Copy + As<usize> + As<u64> + MaybeSerializeDebug;
+	/// AKT staked by other modules.
+	type Collateral: Collateral<Self::AccountId, Self::Balance>;
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
 
 decl_storage! {
 	trait Store for Module<T: Trait> as Akt {
-		Stake get(value): map T::AccountId => T::Balance;
+		/// Total AKT in existence.
+		pub TotalIssuance get(total_issuance) build(|config: &GenesisConfig<T>| {
+			config.balances.iter().fold(Zero::zero(), |acc: T::Balance, &(_, n)| acc + n)
+		}): T::Balance;
+
+		/// AKT of the account, including the locked ones.
+		pub FreeBalance get(free_balance) build(|config: &GenesisConfig<T>| config.balances.clone()): map T::AccountId => T::Balance;
+
+		/// Locks of the AKT of the account.
+		pub Locks get(locks): map T::AccountId => Vec<BalanceLock<T::Balance, T::BlockNumber>>;
+
+		/// Bonded AKT of the account.
+		Ledger get(ledger): map T::AccountId => Option<StakingLedger<T::Balance, T::BlockNumber>>;
+
+		/// Number of blocks the unbonded funds stay locked.
+		UnbondingDuration get(unbonding_duration) config(): T::BlockNumber;
+
+		/// Cap of the total issuance, including the AKT scheduled to be released.
+		MaxIssuance get(max_issuance) config(): T::Balance;
+		/// AKT of the schedules which are not released yet.
+		ScheduledIssuance get(scheduled_issuance): T::Balance;
+		/// Issuance schedules in progress.
+		Schedules get(schedule): map ScheduleId => Option<IssuanceSchedule<T::AccountId, T::Balance, T::BlockNumber>>;
+		NextScheduleId get(next_schedule_id): ScheduleId;
+
+		/// Number of blocks of the reward era.
+		EraLength get(era_length) config(): T::BlockNumber;
+		/// Reward for the paid period of the staked promise, in millionths of the stake.
+		/// The stake of the promise is rewarded at most once per era, however short its periods are.
+		/// Used by the eras without their own rate.
+		RewardRate get(reward_rate) config(): u32;
+		/// Reward rates set for specific eras.
+		EraRewardRate get(era_reward_rate): map EraIndex => Option<u32>;
+		/// AKT set aside for the rewards, they are counted as scheduled issuance.
+		RewardPool get(reward_pool): T::Balance;
+		/// Whether the rewards exceeding the pool are minted, up to the max issuance.
+		InflationRewards get(inflation_rewards) config(): bool;
+		/// promise -> last era the stake of the promise was rewarded in
+		RewardedEra get(rewarded_era): map T::Hash => Option<EraIndex>;
+
+		/// (delegator, issuer) -> AKT delegated to the issuer
+		Delegations get(delegation): map (T::AccountId, T::AccountId) => T::Balance;
+		/// delegator -> issuers the account delegates to
+		DelegatedTo get(delegated_to): map T::AccountId => Vec<T::AccountId>;
+		/// issuer -> delegators of the issuer
+		DelegatorsOf get(delegators_of): map T::AccountId => Vec<T::AccountId>;
+		/// issuer -> total AKT delegated to the issuer
+		TotalDelegated get(total_delegated): map T::AccountId => T::Balance;
+		/// issuer -> part of the delegated AKT held by the stakes of the promises
+		HeldDelegation get(held_delegation): map T::AccountId => T::Balance;
+		/// Part of the promise stake slashed on breach, in millionths of the stake.
+		SlashRate get(slash_rate) config(): u32;
+	}
+	add_extra_genesis {
+		/// (account, AKT balance)
+		config(balances): Vec<(T::AccountId, T::Balance)>;
+
+		build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+			with_storage(storage, || {
+				// the locks are set by the genesis of the modules built before, e.g. the stakes of C2FC:
+				for (who, balance) in &config.balances {
+					if let Some(locked) = <Module<T>>::locks(who).iter().map(|lock| lock.amount).max() {
+						assert!(locked <= *balance, "Invalid AKT genesis: locked AKT of {:?} exceed its balance", who);
+					}
+				}
+			});
+		});
 	}
 }
 
@@ -33,17 +225,1216 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event<T>() = default;
 
+		/// Mint `value` of AKT to the account. Only root can do it.
+		fn issue(origin, to: T::AccountId, value: T::Balance) -> Result {
+			ensure_root(origin)?;
+			Self::ensure_can_issue(value)?;
+
+			drop(<Self as Currency<_>>::deposit_creating(&to, value));
+
+			Self::deposit_event(RawEvent::Issued(to, value));
+
+			Ok(())
+		}
+
+		/// Burn `value` of the own AKT, the locked AKT can't be burnt.
+		fn burn(origin, value: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(!value.is_zero(), "Nothing to burn");
+
+			drop(<Self as Currency<_>>::withdraw(&who, value, WithdrawReason::Transfer, ExistenceRequirement::AllowDeath)?);
+
+			Self::deposit_event(RawEvent::Burned(who, value));
+
+			Ok(())
+		}
+
+		/// Set the cap of the total issuance. Only root can do it.
+		fn set_max_issuance(origin, max: T::Balance) -> Result {
+			ensure_root(origin)?;
+			ensure!(max >= Self::total_issuance().saturating_add(Self::scheduled_issuance()),
+			        "Max issuance is below the issued and scheduled AKT");
+
+			<MaxIssuance<T>>::put(max);
+
+			Self::deposit_event(RawEvent::MaxIssuanceChanged(max));
+
+			Ok(())
+		}
+
+		/// Schedule AKT to be released linearly to the beneficiaries. Only root can do it.
+		fn create_schedule(origin, beneficiaries: Vec<(T::AccountId, T::Balance)>, start: T::BlockNumber, duration: T::BlockNumber) -> Result {
+			ensure_root(origin)?;
+			ensure!(!beneficiaries.is_empty(), "Schedule has no beneficiaries");
+			ensure!(beneficiaries.len() <= MAX_BENEFICIARIES, "Too many beneficiaries of the schedule");
+			ensure!(!duration.is_zero(), "Schedule duration can not be zero");
+
+			let total = beneficiaries.iter().try_fold(T::Balance::zero(), |acc, (_, amount)| acc.checked_add(amount))
+				.ok_or("Overflow of the scheduled AKT")?;
+			Self::ensure_can_issue(total)?;
+
+			let id = Self::next_schedule_id();
+			let next_id = id.checked_add(1).ok_or("No free schedule id")?;
+			let schedule = IssuanceSchedule {
+				beneficiaries: beneficiaries.into_iter().map(|(who, amount)| (who, amount, Zero::zero())).collect(),
+				start,
+				duration,
+			};
+
+			<Schedules<T>>::insert(id, schedule);
+			<NextScheduleId<T>>::put(next_id);
+			<ScheduledIssuance<T>>::mutate(|v| *v = v.saturating_add(total));
+
+			Self::deposit_event(RawEvent::ScheduleCreated(id, total));
+
+			Ok(())
+		}
+
+		/// Release the vested AKT of the schedule to all its beneficiaries. Anyone can do it.
+		fn release(origin, id: ScheduleId) -> Result {
+			let _ = ensure_signed(origin)?;
+			let mut schedule = Self::schedule(id).ok_or("Schedule does not exist")?;
+
+			let now = <system::Module<T>>::block_number();
+			let mut released = T::Balance::zero();
+			for i in 0..schedule.beneficiaries.len() {
+				let (who, total, paid) = schedule.beneficiaries[i].clone();
+				let value = schedule.vested(total, now).saturating_sub(paid);
+				if value.is_zero() {
+					continue;
+				}
+				schedule.beneficiaries[i].2 = paid.saturating_add(value);
+				released = released.saturating_add(value);
+				drop(<Self as Currency<_>>::deposit_creating(&who, value));
+				Self::deposit_event(RawEvent::Released(id, who, value));
+			}
+			ensure!(!released.is_zero(), "Nothing to release");
+
+			<ScheduledIssuance<T>>::mutate(|v| *v = v.saturating_sub(released));
+			if schedule.beneficiaries.iter().all(|(_, total, paid)| paid >= total) {
+				<Schedules<T>>::remove(id);
+			} else {
+				<Schedules<T>>::insert(id, schedule);
+			}
+
+			Ok(())
+		}
+
+		/// Set the reward rate of the era, or the default one if `era` is none. Only root can do it.
+		fn set_reward_rate(origin, era: Option<EraIndex>, rate: u32) -> Result {
+			ensure_root(origin)?;
+			ensure!(rate <= RATE_DENOMINATOR, "Reward rate can not exceed the stake");
+
+			match era {
+				Some(era) => <EraRewardRate<T>>::insert(era, rate),
+				None => <RewardRate<T>>::put(rate),
+			}
+
+			Self::deposit_event(RawEvent::RewardRateChanged(era, rate));
+
+			Ok(())
+		}
+
+		/// Set aside `value` of AKT to pay the rewards. Only root can do it.
+		fn fund_reward_pool(origin, value: T::Balance) -> Result {
+			ensure_root(origin)?;
+			Self::ensure_can_issue(value)?;
+
+			<RewardPool<T>>::mutate(|v| *v = v.saturating_add(value));
+			<ScheduledIssuance<T>>::mutate(|v| *v = v.saturating_add(value));
+
+			Self::deposit_event(RawEvent::RewardPoolFunded(value));
+
+			Ok(())
+		}
+
+		/// Enable or disable minting of the rewards when the pool is empty. Only root can do it.
+		fn set_inflation_rewards(origin, enabled: bool) -> Result {
+			ensure_root(origin)?;
+			<InflationRewards<T>>::put(enabled);
+			Ok(())
+		}
+
+		/// Delegate `value` of the bonded AKT to the issuer.
+		/// The delegation adds to the collateral capacity of the issuer,
+		/// the delegator shares the rewards and the slashes of the issuer.
+		fn delegate(origin, issuer: T::AccountId, value: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(who != issuer, "You can not delegate to yourself");
+
+			let mut ledger = Self::ledger(&who).ok_or("Account is not bonded")?;
+			let value = cmp::min(value, ledger.active - ledger.delegated);
+			ensure!(!value.is_zero(), "Nothing to delegate");
+
+			let key = (who.clone(), issuer.clone());
+			if !<Delegations<T>>::exists(&key) {
+				let mut issuers = Self::delegated_to(&who);
+				ensure!(issuers.len() < MAX_DELEGATIONS, "Too many delegations of the account");
+				let mut delegators = Self::delegators_of(&issuer);
+				ensure!(delegators.len() < MAX_DELEGATORS, "Too many delegators of the issuer");
+
+				issuers.push(issuer.clone());
+				delegators.push(who.clone());
+				<DelegatedTo<T>>::insert(&who, issuers);
+				<DelegatorsOf<T>>::insert(&issuer, delegators);
+			}
+
+			<Delegations<T>>::mutate(&key, |v| *v = v.saturating_add(value));
+			<TotalDelegated<T>>::mutate(&issuer, |v| *v = v.saturating_add(value));
+			ledger.delegated = ledger.delegated.saturating_add(value);
+			<Ledger<T>>::insert(&who, ledger);
+
+			Self::deposit_event(RawEvent::Delegated(who, issuer, value));
+
+			Ok(())
+		}
+
+		/// Return `value` of the AKT delegated to the issuer to the bonded AKT of the delegator.
+		/// The AKT held by the stakes of the issuer can't be undelegated until the stakes are withdrawn.
+		fn undelegate(origin, issuer: T::AccountId, value: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+
+			let value = cmp::min(value, Self::delegation((who.clone(), issuer.clone())));
+			ensure!(!value.is_zero(), "Nothing to undelegate");
+			let unheld = Self::total_delegated(&issuer).saturating_sub(Self::held_delegation(&issuer));
+			ensure!(value <= unheld, "Delegated AKT are held by the stakes of the issuer");
+
+			Self::reduce_delegation(&who, &issuer, value);
+
+			Self::deposit_event(RawEvent::Undelegated(who, issuer, value));
+
+			Ok(())
+		}
+
+		/// Bond `value` of the free balance, at most the whole free balance.
+		/// The AKT staked by other modules can't be bonded.
+		fn bond(origin, value: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+
+			let mut ledger = Self::ledger(&who).unwrap_or_default();
+			let free = Self::free_balance(&who)
+				.saturating_sub(ledger.total)
+				.saturating_sub(T::Collateral::staked(&who));
+			let value = cmp::min(value, free);
+			ensure!(!value.is_zero(), "Nothing to bond");
+
+			ledger.total = ledger.total.saturating_add(value);
+			ledger.active = ledger.active.saturating_add(value);
+			Self::update_ledger(&who, ledger);
+
+			Self::deposit_event(RawEvent::Stake(value, who));
+
+			Ok(())
+		}
+
+		/// Schedule `value` of the bonded funds to be unlocked after the unbonding period.
+		fn unbond(origin, value: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+
+			let mut ledger = Self::ledger(&who).ok_or("Account is not bonded")?;
+			ensure!(ledger.unlocking.len() < MAX_UNLOCKING_CHUNKS, "Too many unlocking chunks, withdraw the unbonded first");
+
+			// the delegated AKT should be undelegated first:
+			let value = cmp::min(value, ledger.active - ledger.delegated);
+			ensure!(!value.is_zero(), "Nothing to unbond");
+
+			let until = <system::Module<T>>::block_number().saturating_add(Self::unbonding_duration());
+			ledger.active = ledger.active - value;
+			ledger.unlocking.push(UnlockChunk { value, until });
+			Self::update_ledger(&who, ledger);
+
+			Self::deposit_event(RawEvent::Unbonded(value, who, until));
+
+			Ok(())
+		}
+
+		/// Unlock the funds which passed the unbonding period.
+		fn withdraw_unbonded(origin) -> Result {
+			let who = ensure_signed(origin)?;
+
+			let ledger = Self::ledger(&who).ok_or("Account is not bonded")?;
+			let before = ledger.total;
+			let ledger = ledger.consolidate_unlocked(<system::Module<T>>::block_number());
+			let value = before.saturating_sub(ledger.total);
+			ensure!(!value.is_zero(), "Nothing to withdraw");
+
+			if ledger.total.is_zero() {
+				<Self as LockableCurrency<_>>::remove_lock(STAKE_ID, &who);
+				<Ledger<T>>::remove(&who);
+			} else {
+				Self::update_ledger(&who, ledger);
+			}
+
+			Self::deposit_event(RawEvent::Withdraw(value, who));
+
+			Ok(())
+		}
 	}
 }
 
 
 decl_event!(
 	pub enum Event<T>
-		where Balance = <T as balances::Trait>::Balance,
+		where Balance = <T as Trait>::Balance,
 		      AccountId = <T as system::Trait>::AccountId,
+		      BlockNumber = <T as system::Trait>::BlockNumber,
+		      Hash = <T as system::Trait>::Hash,
 	{
-		Issued(u16, AccountId, u64),
+		/// (who:AccountId, value:Balance)
+		Issued(AccountId, Balance),
+		/// (who:AccountId, value:Balance)
+		Burned(AccountId, Balance),
+		/// (max:Balance)
+		MaxIssuanceChanged(Balance),
+		/// (schedule_id:ScheduleId, total:Balance)
+		ScheduleCreated(ScheduleId, Balance),
+		/// (schedule_id:ScheduleId, who:AccountId, value:Balance)
+		Released(ScheduleId, AccountId, Balance),
+		/// (era:Option<EraIndex>, rate:u32), none era is the default rate
+		RewardRateChanged(Option<EraIndex>, u32),
+		/// (value:Balance)
+		RewardPoolFunded(Balance),
+		/// (promise_id:Hash, who:AccountId, value:Balance)
+		Rewarded(Hash, AccountId, Balance),
+		/// (delegator:AccountId, issuer:AccountId, value:Balance)
+		Delegated(AccountId, AccountId, Balance),
+		/// (delegator:AccountId, issuer:AccountId, value:Balance)
+		Undelegated(AccountId, AccountId, Balance),
+		/// (promise_id:Hash, who:AccountId, value:Balance)
+		Slashed(Hash, AccountId, Balance),
+		/// (value:Balance, who:AccountId)
 		Stake(Balance, AccountId),
+		/// (value:Balance, who:AccountId, until:BlockNumber)
+		Unbonded(Balance, AccountId, BlockNumber),
+		/// (value:Balance, who:AccountId)
 		Withdraw(Balance, AccountId),
 	}
 );
+
+
+impl<T: Trait> Module<T> {
+	/// Ensure `value` of AKT can be issued without exceeding the max issuance.
+	fn ensure_can_issue(value: T::Balance) -> Result {
+		let issued = Self::total_issuance().checked_add(&Self::scheduled_issuance())
+			.and_then(|issued| issued.checked_add(&value))
+			.ok_or("Overflow of the AKT issuance")?;
+		ensure!(issued <= Self::max_issuance(), "AKT issuance exceeds the max issuance");
+		Ok(())
+	}
+
+	/// Index of the current reward era.
+	pub fn current_era() -> EraIndex {
+		let era_length = Self::era_length();
+		if era_length.is_zero() {
+			return 0;
+		}
+		(<system::Module<T>>::block_number() / era_length).as_() as EraIndex
+	}
+
+	/// Reward rate of the era.
+	pub fn reward_rate_of(era: EraIndex) -> u32 {
+		Self::era_reward_rate(era).unwrap_or_else(Self::reward_rate)
+	}
+
+	/// Pay `value` of reward from the pool, the rest is minted if the inflation rewards are enabled.
+	fn pay_reward(who: &T::AccountId, value: T::Balance) -> T::Balance {
+		let pool = Self::reward_pool();
+		let from_pool = cmp::min(pool, value);
+		let minted = if Self::inflation_rewards() {
+			let issued = Self::total_issuance().saturating_add(Self::scheduled_issuance());
+			cmp::min(value - from_pool, Self::max_issuance().saturating_sub(issued))
+		} else {
+			Zero::zero()
+		};
+
+		<RewardPool<T>>::put(pool - from_pool);
+		<ScheduledIssuance<T>>::mutate(|v| *v = v.saturating_sub(from_pool));
+
+		let paid = from_pool + minted;
+		drop(<Self as Currency<_>>::deposit_creating(who, paid));
+		paid
+	}
+
+	/// Split `value` among the delegators of the issuer in proportion to their delegations.
+	fn delegators_share(issuer: &T::AccountId, value: T::Balance) -> Vec<(T::AccountId, T::Balance)> {
+		let total = Self::total_delegated(issuer);
+		Self::delegators_of(issuer).into_iter().map(|delegator| {
+			let amount = Self::delegation((delegator.clone(), issuer.clone()));
+			(delegator, share(value, amount, total))
+		}).collect()
+	}
+
+	/// Reduce the delegation and the delegated part of the ledger of the delegator.
+	fn reduce_delegation(who: &T::AccountId, issuer: &T::AccountId, value: T::Balance) {
+		let key = (who.clone(), issuer.clone());
+		let left = Self::delegation(&key).saturating_sub(value);
+		if left.is_zero() {
+			<Delegations<T>>::remove(&key);
+			<DelegatedTo<T>>::mutate(who, |issuers| issuers.retain(|i| i != issuer));
+			<DelegatorsOf<T>>::mutate(issuer, |delegators| delegators.retain(|d| d != who));
+		} else {
+			<Delegations<T>>::insert(&key, left);
+		}
+		<TotalDelegated<T>>::mutate(issuer, |v| *v = v.saturating_sub(value));
+
+		if let Some(mut ledger) = Self::ledger(who) {
+			ledger.delegated = ledger.delegated.saturating_sub(value);
+			<Ledger<T>>::insert(who, ledger);
+		}
+	}
+
+	/// Slash `value` of the AKT delegated by `who` to the issuer.
+	fn slash_delegation(who: &T::AccountId, issuer: &T::AccountId, value: T::Balance) -> T::Balance {
+		let value = cmp::min(value, Self::delegation((who.clone(), issuer.clone())));
+		Self::reduce_delegation(who, issuer, value);
+
+		let mut ledger = match Self::ledger(who) {
+			Some(ledger) => ledger,
+			None => return Zero::zero(),
+		};
+		ledger.total = ledger.total.saturating_sub(value);
+		ledger.active = ledger.active.saturating_sub(value);
+		// the lock is reduced before the slash, so it doesn't hold the slashed AKT:
+		if ledger.total.is_zero() {
+			<Self as LockableCurrency<_>>::remove_lock(STAKE_ID, who);
+			<Ledger<T>>::remove(who);
+		} else {
+			Self::update_ledger(who, ledger);
+		}
+
+		let (imbalance, _) = <Self as Currency<_>>::slash(who, value);
+		let slashed = imbalance.peek();
+		drop(imbalance);
+		slashed
+	}
+
+	/// Store the ledger and lock its total.
+	fn update_ledger(who: &T::AccountId, ledger: StakingLedger<T::Balance, T::BlockNumber>) {
+		<Self as LockableCurrency<_>>::set_lock(STAKE_ID, who, ledger.total, T::BlockNumber::max_value(), WithdrawReasons::all());
+		<Ledger<T>>::insert(who, ledger);
+	}
+}
+
+
+impl<T: Trait> Currency<T::AccountId> for Module<T> {
+	type Balance = T::Balance;
+	type PositiveImbalance = PositiveImbalance<T>;
+	type NegativeImbalance = NegativeImbalance<T>;
+
+	fn total_balance(who: &T::AccountId) -> Self::Balance {
+		<FreeBalance<T>>::get(who)
+	}
+
+	fn total_issuance() -> Self::Balance {
+		<TotalIssuance<T>>::get()
+	}
+
+	/// AKT accounts are never reaped.
+	fn minimum_balance() -> Self::Balance {
+		Zero::zero()
+	}
+
+	fn free_balance(who: &T::AccountId) -> Self::Balance {
+		<FreeBalance<T>>::get(who)
+	}
+
+	fn ensure_can_withdraw(
+		who: &T::AccountId,
+		_amount: T::Balance,
+		reason: WithdrawReason,
+		new_balance: T::Balance,
+	) -> Result {
+		let now = <system::Module<T>>::block_number();
+		if Self::locks(who).into_iter()
+			.all(|l| now >= l.until || new_balance >= l.amount || !l.reasons.contains(reason))
+		{
+			Ok(())
+		} else {
+			Err("AKT are locked")
+		}
+	}
+
+	fn transfer(source: &T::AccountId, dest: &T::AccountId, value: Self::Balance) -> Result {
+		let new_from_balance = <FreeBalance<T>>::get(source).checked_sub(&value).ok_or("AKT balance too low to send value")?;
+		Self::ensure_can_withdraw(source, value, WithdrawReason::Transfer, new_from_balance)?;
+
+		if source != dest {
+			let new_to_balance = <FreeBalance<T>>::get(dest).checked_add(&value).ok_or("AKT balance overflow")?;
+			<FreeBalance<T>>::insert(source, new_from_balance);
+			<FreeBalance<T>>::insert(dest, new_to_balance);
+		}
+
+		Ok(())
+	}
+
+	fn withdraw(
+		who: &T::AccountId,
+		value: Self::Balance,
+		reason: WithdrawReason,
+		_liveness: ExistenceRequirement,
+	) -> result::Result<Self::NegativeImbalance, &'static str> {
+		let new_balance = <FreeBalance<T>>::get(who).checked_sub(&value).ok_or("AKT balance too low to withdraw")?;
+		Self::ensure_can_withdraw(who, value, reason, new_balance)?;
+		<FreeBalance<T>>::insert(who, new_balance);
+		Ok(NegativeImbalance::new(value))
+	}
+
+	fn slash(who: &T::AccountId, value: Self::Balance) -> (Self::NegativeImbalance, Self::Balance) {
+		let free = <FreeBalance<T>>::get(who);
+		let slashed = cmp::min(free, value);
+		<FreeBalance<T>>::insert(who, free - slashed);
+		(NegativeImbalance::new(slashed), value - slashed)
+	}
+
+	fn deposit_into_existing(who: &T::AccountId, value: Self::Balance) -> result::Result<Self::PositiveImbalance, &'static str> {
+		ensure!(<FreeBalance<T>>::exists(who), "AKT beneficiary account must pre-exist");
+		let new_balance = <FreeBalance<T>>::get(who).checked_add(&value).ok_or("AKT balance overflow")?;
+		<FreeBalance<T>>::insert(who, new_balance);
+		Ok(PositiveImbalance::new(value))
+	}
+
+	fn deposit_creating(who: &T::AccountId, value: Self::Balance) -> Self::PositiveImbalance {
+		match <FreeBalance<T>>::get(who).checked_add(&value) {
+			Some(new_balance) => {
+				<FreeBalance<T>>::insert(who, new_balance);
+				PositiveImbalance::new(value)
+			},
+			None => PositiveImbalance::zero(),
+		}
+	}
+
+	fn make_free_balance_be(who: &T::AccountId, balance: Self::Balance) -> (
+		SignedImbalance<Self::Balance, Self::PositiveImbalance>,
+		UpdateBalanceOutcome
+	) {
+		let original = <FreeBalance<T>>::get(who);
+		let imbalance = if original <= balance {
+			SignedImbalance::Positive(PositiveImbalance::new(balance - original))
+		} else {
+			SignedImbalance::Negative(NegativeImbalance::new(original - balance))
+		};
+		<FreeBalance<T>>::insert(who, balance);
+		(imbalance, UpdateBalanceOutcome::Updated)
+	}
+}
+
+impl<T: Trait> LockableCurrency<T::AccountId> for Module<T> {
+	type Moment = T::BlockNumber;
+
+	fn set_lock(
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		until: T::BlockNumber,
+		reasons: WithdrawReasons,
+	) {
+		let now = <system::Module<T>>::block_number();
+		let mut new_lock = Some(BalanceLock { id, amount, until, reasons });
+		let mut locks = Self::locks(who).into_iter().filter_map(|lock|
+			if lock.id == id {
+				new_lock.take()
+			} else if lock.until > now {
+				Some(lock)
+			} else {
+				None
+			}).collect::<Vec<_>>();
+		if let Some(lock) = new_lock {
+			locks.push(lock)
+		}
+		<Locks<T>>::insert(who, locks);
+	}
+
+	fn extend_lock(
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		until: T::BlockNumber,
+		reasons: WithdrawReasons,
+	) {
+		let now = <system::Module<T>>::block_number();
+		let mut new_lock = Some(BalanceLock { id, amount, until, reasons });
+		let mut locks = Self::locks(who).into_iter().filter_map(|lock|
+			if lock.id == id {
+				new_lock.take().map(|nl| BalanceLock {
+					id: lock.id,
+					amount: lock.amount.max(nl.amount),
+					until: lock.until.max(nl.until),
+					reasons: lock.reasons | nl.reasons,
+				})
+			} else if lock.until > now {
+				Some(lock)
+			} else {
+				None
+			}).collect::<Vec<_>>();
+		if let Some(lock) = new_lock {
+			locks.push(lock)
+		}
+		<Locks<T>>::insert(who, locks);
+	}
+
+	fn remove_lock(id: LockIdentifier, who: &T::AccountId) {
+		let now = <system::Module<T>>::block_number();
+		let locks = Self::locks(who).into_iter().filter(|lock| lock.until > now && lock.id != id).collect::<Vec<_>>();
+		<Locks<T>>::insert(who, locks);
+	}
+}
+
+
+/// Part of `value` in millionths.
+fn per_million<Balance: SimpleArithmetic + Copy>(value: Balance, rate: u32) -> Balance {
+	let denominator = Balance::sa(RATE_DENOMINATOR as u64);
+	let rate = Balance::sa(rate as u64);
+	// split to avoid the overflow of `value * rate`:
+	value / denominator * rate + value % denominator * rate / denominator
+}
+
+/// Share of `value` proportional to `part` of `total`, with precision of millionths.
+fn share<Balance: SimpleArithmetic + Copy>(value: Balance, part: Balance, total: Balance) -> Balance {
+	if total.is_zero() {
+		return Zero::zero();
+	}
+	let denominator = Balance::sa(RATE_DENOMINATOR as u64);
+	let rate = part.checked_mul(&denominator)
+		.map(|p| p / total)
+		.unwrap_or_else(|| part / (total / denominator));
+	per_million(value, cmp::min(rate.as_(), RATE_DENOMINATOR as u64) as u32)
+}
+
+impl<T: Trait> OnPeriodPaid<T::AccountId, T::Hash, T::Balance> for Module<T> {
+	fn on_period_paid(promise_id: T::Hash, issuer: &T::AccountId, own: T::Balance, delegated: T::Balance) {
+		// the stake is rewarded once per era, so the short periods don't multiply the reward:
+		let era = Self::current_era();
+		if Self::rewarded_era(promise_id) == Some(era) {
+			return;
+		}
+		<RewardedEra<T>>::insert(promise_id, era);
+		let rate = Self::reward_rate_of(era);
+
+		// the reward of the own stake goes to the issuer, of the held AKT is shared by the delegators:
+		let mut rewards = vec![(issuer.clone(), per_million(own, rate))];
+		let reward = per_million(delegated, rate);
+		if !reward.is_zero() {
+			rewards.extend(Self::delegators_share(issuer, reward));
+		}
+		for (who, reward) in rewards {
+			if reward.is_zero() {
+				continue;
+			}
+			let paid = Self::pay_reward(&who, reward);
+			if !paid.is_zero() {
+				Self::deposit_event(RawEvent::Rewarded(promise_id, who, paid));
+			}
+		}
+	}
+}
+
+impl<T: Trait> Backing<T::AccountId, T::Hash, T::Balance> for Module<T> {
+	fn delegated(issuer: &T::AccountId) -> T::Balance {
+		Self::total_delegated(issuer).saturating_sub(Self::held_delegation(issuer))
+	}
+
+	fn bonded(who: &T::AccountId) -> T::Balance {
+		Self::ledger(who).map(|ledger| ledger.total).unwrap_or_default()
+	}
+
+	fn hold(issuer: &T::AccountId, value: T::Balance) {
+		<HeldDelegation<T>>::mutate(issuer, |v| *v = v.saturating_add(value));
+	}
+
+	fn release(issuer: &T::AccountId, value: T::Balance) {
+		<HeldDelegation<T>>::mutate(issuer, |v| *v = v.saturating_sub(value));
+	}
+
+	fn on_breach(promise_id: T::Hash, issuer: &T::AccountId, own: T::Balance, delegated: T::Balance) -> (T::Balance, T::Balance) {
+		let rate = Self::slash_rate();
+
+		let mut own_slashed = T::Balance::zero();
+		let slash = per_million(own, rate);
+		if !slash.is_zero() {
+			let (imbalance, _) = <Self as Currency<_>>::slash(issuer, slash);
+			own_slashed = imbalance.peek();
+			drop(imbalance);
+			if !own_slashed.is_zero() {
+				Self::deposit_event(RawEvent::Slashed(promise_id, issuer.clone(), own_slashed));
+			}
+		}
+
+		// the slash of the held AKT is shared by the delegators:
+		let mut delegated_slashed = T::Balance::zero();
+		let slash = per_million(delegated, rate);
+		if slash.is_zero() {
+			return (own_slashed, delegated_slashed);
+		}
+		for (who, value) in Self::delegators_share(issuer, slash) {
+			if value.is_zero() {
+				continue;
+			}
+			let slashed = Self::slash_delegation(&who, issuer, value);
+			if !slashed.is_zero() {
+				delegated_slashed = delegated_slashed.saturating_add(slashed);
+				Self::deposit_event(RawEvent::Slashed(promise_id, who, slashed));
+			}
+		}
+		// the slashed AKT left the delegations, so they are not held anymore:
+		<HeldDelegation<T>>::mutate(issuer, |v| *v = v.saturating_sub(delegated_slashed));
+
+		(own_slashed, delegated_slashed)
+	}
+}
+
+
+/// Imbalances of the AKT, they square up `TotalIssuance` when dropped.
+mod imbalances {
+	use super::{result, Imbalance, Saturating, StorageValue, Trait, Zero, TotalIssuance};
+	use rstd::mem;
+
+	/// Opaque, move-only struct with private fields that serves as a token denoting that
+	/// AKT have been created without any equal and opposite accounting.
+	#[must_use]
+	pub struct PositiveImbalance<T: Trait>(T::Balance);
+
+	impl<T: Trait> PositiveImbalance<T> {
+		pub fn new(amount: T::Balance) -> Self {
+			PositiveImbalance(amount)
+		}
+	}
+
+	/// Opaque, move-only struct with private fields that serves as a token denoting that
+	/// AKT have been destroyed without any equal and opposite accounting.
+	#[must_use]
+	pub struct NegativeImbalance<T: Trait>(T::Balance);
+
+	impl<T: Trait> NegativeImbalance<T> {
+		pub fn new(amount: T::Balance) -> Self {
+			NegativeImbalance(amount)
+		}
+	}
+
+	impl<T: Trait> Imbalance<T::Balance> for PositiveImbalance<T> {
+		type Opposite = NegativeImbalance<T>;
+
+		fn zero() -> Self {
+			Self(Zero::zero())
+		}
+		fn drop_zero(self) -> result::Result<(), Self> {
+			if self.0.is_zero() {
+				Ok(())
+			} else {
+				Err(self)
+			}
+		}
+		fn split(self, amount: T::Balance) -> (Self, Self) {
+			let first = self.0.min(amount);
+			let second = self.0 - first;
+
+			mem::forget(self);
+			(Self(first), Self(second))
+		}
+		fn merge(mut self, other: Self) -> Self {
+			self.0 = self.0.saturating_add(other.0);
+			mem::forget(other);
+
+			self
+		}
+		fn subsume(&mut self, other: Self) {
+			self.0 = self.0.saturating_add(other.0);
+			mem::forget(other);
+		}
+		fn offset(self, other: Self::Opposite) -> result::Result<Self, Self::Opposite> {
+			let (a, b) = (self.0, other.0);
+			mem::forget((self, other));
+
+			if a >= b {
+				Ok(Self(a - b))
+			} else {
+				Err(NegativeImbalance::new(b - a))
+			}
+		}
+		fn peek(&self) -> T::Balance {
+			self.0
+		}
+	}
+
+	impl<T: Trait> Imbalance<T::Balance> for NegativeImbalance<T> {
+		type Opposite = PositiveImbalance<T>;
+
+		fn zero() -> Self {
+			Self(Zero::zero())
+		}
+		fn drop_zero(self) -> result::Result<(), Self> {
+			if self.0.is_zero() {
+				Ok(())
+			} else {
+				Err(self)
+			}
+		}
+		fn split(self, amount: T::Balance) -> (Self, Self) {
+			let first = self.0.min(amount);
+			let second = self.0 - first;
+
+			mem::forget(self);
+			(Self(first), Self(second))
+		}
+		fn merge(mut self, other: Self) -> Self {
+			self.0 = self.0.saturating_add(other.0);
+			mem::forget(other);
+
+			self
+		}
+		fn subsume(&mut self, other: Self) {
+			self.0 = self.0.saturating_add(other.0);
+			mem::forget(other);
+		}
+		fn offset(self, other: Self::Opposite) -> result::Result<Self, Self::Opposite> {
+			let (a, b) = (self.0, other.0);
+			mem::forget((self, other));
+
+			if a >= b {
+				Ok(Self(a - b))
+			} else {
+				Err(PositiveImbalance::new(b - a))
+			}
+		}
+		fn peek(&self) -> T::Balance {
+			self.0
+		}
+	}
+
+	impl<T: Trait> Drop for PositiveImbalance<T> {
+		/// Basic drop handler will just square up the total issuance.
+		fn drop(&mut self) {
+			<TotalIssuance<T>>::mutate(|v| *v = v.saturating_add(self.0));
+		}
+	}
+
+	impl<T: Trait> Drop for NegativeImbalance<T> {
+		/// Basic drop handler will just square up the total issuance.
+		fn drop(&mut self) {
+			<TotalIssuance<T>>::mutate(|v| *v = v.saturating_sub(self.0));
+		}
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok, assert_err};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup},
+		testing::{Digest, DigestItem, Header}
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl Trait for Test {
+		type Balance = u64;
+		type Collateral = ();
+		type Event = ();
+	}
+
+	type Akt = Module<Test>;
+	type System = system::Module<Test>;
+
+	/// Issuer of the staked promises.
+	const ISSUER: u64 = 1;
+	/// Delegators of the issuer.
+	const ALICE: u64 = 2;
+	const BOB: u64 = 3;
+
+	/// AKT balance of every account at genesis.
+	const ENDOWMENT: u64 = 1_000_000;
+	const UNBONDING: u64 = 10;
+	const MAX_ISSUANCE: u64 = 10 * ENDOWMENT;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let (mut t, mut c) = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		GenesisConfig::<Test> {
+			balances: vec![(ISSUER, ENDOWMENT), (ALICE, ENDOWMENT), (BOB, ENDOWMENT)],
+			unbonding_duration: UNBONDING,
+			max_issuance: MAX_ISSUANCE,
+			era_length: 100,
+			reward_rate: 0,
+			inflation_rewards: false,
+			slash_rate: 0,
+		}.assimilate_storage(&mut t, &mut c).unwrap();
+		let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+		with_externalities(&mut ext, || System::set_block_number(1));
+		ext
+	}
+
+	fn ledger(total: u64, active: u64, delegated: u64, unlocking: Vec<(u64, u64)>) -> StakingLedger<u64, u64> {
+		StakingLedger {
+			total,
+			active,
+			delegated,
+			unlocking: unlocking.into_iter().map(|(value, until)| UnlockChunk { value, until }).collect(),
+		}
+	}
+
+	#[test]
+	fn bond_locks_the_bonded_akt() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 400));
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 100));
+			assert_eq!(Akt::ledger(ISSUER), Some(ledger(500, 500, 0, vec![])));
+
+			assert_err!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 499), "AKT are locked");
+			assert_ok!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 500));
+		});
+	}
+
+	#[test]
+	fn bond_is_limited_by_free_balance() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 2 * ENDOWMENT));
+			assert_eq!(Akt::ledger(ISSUER), Some(ledger(ENDOWMENT, ENDOWMENT, 0, vec![])));
+			assert_err!(Akt::bond(Origin::signed(ISSUER), 1), "Nothing to bond");
+		});
+	}
+
+	#[test]
+	fn unbonded_akt_is_locked_for_unbonding_duration() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::unbond(Origin::signed(ISSUER), 100), "Account is not bonded");
+
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 500));
+			assert_ok!(Akt::unbond(Origin::signed(ISSUER), 200));
+			assert_eq!(Akt::ledger(ISSUER), Some(ledger(500, 300, 0, vec![(200, 1 + UNBONDING)])));
+
+			System::set_block_number(UNBONDING);
+			assert_err!(Akt::withdraw_unbonded(Origin::signed(ISSUER)), "Nothing to withdraw");
+			assert_err!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 499), "AKT are locked");
+
+			System::set_block_number(1 + UNBONDING);
+			assert_ok!(Akt::withdraw_unbonded(Origin::signed(ISSUER)));
+			assert_eq!(Akt::ledger(ISSUER), Some(ledger(300, 300, 0, vec![])));
+			assert_ok!(Akt::burn(Origin::signed(ISSUER), ENDOWMENT - 300));
+		});
+	}
+
+	#[test]
+	fn unbond_is_limited_by_active_akt() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 500));
+			assert_ok!(Akt::unbond(Origin::signed(ISSUER), 1_000));
+			assert_eq!(Akt::ledger(ISSUER), Some(ledger(500, 0, 0, vec![(500, 1 + UNBONDING)])));
+			assert_err!(Akt::unbond(Origin::signed(ISSUER), 1), "Nothing to unbond");
+
+			// the fully withdrawn ledger is removed with its lock:
+			System::set_block_number(1 + UNBONDING);
+			assert_ok!(Akt::withdraw_unbonded(Origin::signed(ISSUER)));
+			assert_eq!(Akt::ledger(ISSUER), None);
+			assert!(Akt::locks(ISSUER).is_empty());
+		});
+	}
+
+	#[test]
+	fn unlocking_chunks_are_limited() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::bond(Origin::signed(ISSUER), 500));
+			for _ in 0..MAX_UNLOCKING_CHUNKS {
+				assert_ok!(Akt::unbond(Origin::signed(ISSUER), 1));
+			}
+			assert_err!(
+				Akt::unbond(Origin::signed(ISSUER), 1),
+				"Too many unlocking chunks, withdraw the unbonded first"
+			);
+		});
+	}
+
+	#[test]
+	fn issue_is_limited_by_max_issuance() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::issue(Origin::signed(ISSUER), ISSUER, 1), "bad origin: expected to be a root origin");
+			assert_ok!(Akt::issue(Origin::ROOT, ISSUER, 1_000));
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 1_000);
+			assert_eq!(Akt::total_issuance(), 3 * ENDOWMENT + 1_000);
+
+			let left = MAX_ISSUANCE - Akt::total_issuance();
+			assert_err!(Akt::issue(Origin::ROOT, ALICE, left + 1), "AKT issuance exceeds the max issuance");
+			assert_ok!(Akt::issue(Origin::ROOT, ALICE, left));
+			assert_eq!(Akt::total_issuance(), MAX_ISSUANCE);
+		});
+	}
+
+	#[test]
+	fn burn_reduces_total_issuance() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::burn(Origin::signed(ISSUER), 0), "Nothing to burn");
+			assert_ok!(Akt::burn(Origin::signed(ISSUER), 1_000));
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 1_000);
+			assert_eq!(Akt::total_issuance(), 3 * ENDOWMENT - 1_000);
+		});
+	}
+
+	#[test]
+	fn max_issuance_covers_issued_and_scheduled() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::create_schedule(Origin::ROOT, vec![(ALICE, 1_000)], 10, 100));
+			assert_err!(
+				Akt::set_max_issuance(Origin::ROOT, 3 * ENDOWMENT + 999),
+				"Max issuance is below the issued and scheduled AKT"
+			);
+			assert_ok!(Akt::set_max_issuance(Origin::ROOT, 3 * ENDOWMENT + 1_000));
+
+			// the scheduled AKT count towards the cap:
+			assert_err!(Akt::issue(Origin::ROOT, ALICE, 1), "AKT issuance exceeds the max issuance");
+			assert_err!(
+				Akt::create_schedule(Origin::ROOT, vec![(BOB, 1)], 10, 100),
+				"AKT issuance exceeds the max issuance"
+			);
+		});
+	}
+
+	#[test]
+	fn schedules_are_validated() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::create_schedule(Origin::ROOT, vec![], 10, 100), "Schedule has no beneficiaries");
+			assert_err!(Akt::create_schedule(Origin::ROOT, vec![(ALICE, 1)], 10, 0), "Schedule duration can not be zero");
+			let many = (0..MAX_BENEFICIARIES as u64 + 1).map(|who| (who, 1)).collect();
+			assert_err!(Akt::create_schedule(Origin::ROOT, many, 10, 100), "Too many beneficiaries of the schedule");
+			assert_err!(
+				Akt::create_schedule(Origin::ROOT, vec![(ALICE, u64::max_value()), (BOB, 1)], 10, 100),
+				"Overflow of the scheduled AKT"
+			);
+			assert_eq!(Akt::next_schedule_id(), 0);
+		});
+	}
+
+	#[test]
+	fn vested_is_linear_between_start_and_end() {
+		let schedule = IssuanceSchedule::<u64, u64, u64> { beneficiaries: vec![], start: 10, duration: 100 };
+		assert_eq!(schedule.vested(1_000, 5), 0);
+		assert_eq!(schedule.vested(1_000, 10), 0);
+		assert_eq!(schedule.vested(1_000, 35), 250);
+		assert_eq!(schedule.vested(1_000, 110), 1_000);
+		assert_eq!(schedule.vested(1_000, 200), 1_000);
+		// no overflow of the large totals:
+		assert_eq!(schedule.vested(u64::max_value(), 60), u64::max_value() / 2);
+	}
+
+	#[test]
+	fn release_pays_vested_akt_until_the_schedule_ends() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::create_schedule(Origin::ROOT, vec![(ALICE, 1_000), (BOB, 500)], 10, 100));
+			assert_eq!(Akt::scheduled_issuance(), 1_500);
+			assert_err!(Akt::release(Origin::signed(ISSUER), 0), "Nothing to release");
+			assert_err!(Akt::release(Origin::signed(ISSUER), 1), "Schedule does not exist");
+
+			System::set_block_number(60);
+			assert_ok!(Akt::release(Origin::signed(ISSUER), 0));
+			assert_eq!(Akt::free_balance(&ALICE), ENDOWMENT + 500);
+			assert_eq!(Akt::free_balance(&BOB), ENDOWMENT + 250);
+			assert_eq!(Akt::scheduled_issuance(), 750);
+			assert_err!(Akt::release(Origin::signed(ISSUER), 0), "Nothing to release");
+
+			System::set_block_number(200);
+			assert_ok!(Akt::release(Origin::signed(ISSUER), 0));
+			assert_eq!(Akt::free_balance(&ALICE), ENDOWMENT + 1_000);
+			assert_eq!(Akt::free_balance(&BOB), ENDOWMENT + 500);
+			assert_eq!(Akt::scheduled_issuance(), 0);
+			assert_eq!(Akt::total_issuance(), 3 * ENDOWMENT + 1_500);
+			assert_eq!(Akt::schedule(0), None);
+		});
+	}
+
+	fn period_paid(issuer: u64, stake: u64) {
+		<Akt as OnPeriodPaid<u64, H256, u64>>::on_period_paid(H256::zero(), &issuer, stake, 0);
+	}
+
+	#[test]
+	fn reward_rate_is_set_per_era() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::set_reward_rate(Origin::ROOT, None, RATE_DENOMINATOR + 1), "Reward rate can not exceed the stake");
+			assert_ok!(Akt::set_reward_rate(Origin::ROOT, None, 1_000));
+			assert_ok!(Akt::set_reward_rate(Origin::ROOT, Some(2), 5_000));
+			assert_eq!(Akt::reward_rate_of(1), 1_000);
+			assert_eq!(Akt::reward_rate_of(2), 5_000);
+
+			assert_eq!(Akt::current_era(), 0);
+			System::set_block_number(250);
+			assert_eq!(Akt::current_era(), 2);
+
+			period_paid(ISSUER, 10_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT);
+		});
+	}
+
+	#[test]
+	fn rewards_are_paid_from_pool_then_minted() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::fund_reward_pool(Origin::ROOT, MAX_ISSUANCE), "AKT issuance exceeds the max issuance");
+			assert_ok!(Akt::fund_reward_pool(Origin::ROOT, 100));
+			assert_eq!(Akt::scheduled_issuance(), 100);
+			assert_ok!(Akt::set_reward_rate(Origin::ROOT, None, 100_000));
+
+			// the pool is spent, the rest is not paid without the inflation:
+			period_paid(ISSUER, 2_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 100);
+			assert_eq!(Akt::reward_pool(), 0);
+			assert_eq!(Akt::scheduled_issuance(), 0);
+			assert_eq!(Akt::total_issuance(), 3 * ENDOWMENT + 100);
+
+			assert_ok!(Akt::set_inflation_rewards(Origin::ROOT, true));
+			System::set_block_number(101);
+			period_paid(ISSUER, 2_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 300);
+
+			// the minted rewards are limited by the max issuance:
+			assert_ok!(Akt::set_max_issuance(Origin::ROOT, Akt::total_issuance() + 50));
+			System::set_block_number(201);
+			period_paid(ISSUER, 2_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 350);
+			assert_eq!(Akt::total_issuance(), Akt::max_issuance());
+		});
+	}
+
+	#[test]
+	fn stake_is_rewarded_once_per_era() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::set_reward_rate(Origin::ROOT, None, 1_000));
+			assert_ok!(Akt::set_inflation_rewards(Origin::ROOT, true));
+
+			period_paid(ISSUER, 10_000);
+			period_paid(ISSUER, 10_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 10);
+
+			// the other promises are rewarded in the same era:
+			<Akt as OnPeriodPaid<u64, H256, u64>>::on_period_paid(H256::repeat_byte(1), &ISSUER, 10_000, 0);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 20);
+
+			System::set_block_number(100);
+			period_paid(ISSUER, 10_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 30);
+		});
+	}
+
+	#[test]
+	fn zero_reward_rate_pays_nothing() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Akt::fund_reward_pool(Origin::ROOT, 100));
+			period_paid(ISSUER, 2_000);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT);
+			assert_eq!(Akt::reward_pool(), 100);
+		});
+	}
+
+	fn delegate(who: u64, value: u64) {
+		assert_ok!(Akt::bond(Origin::signed(who), value));
+		assert_ok!(Akt::delegate(Origin::signed(who), ISSUER, value));
+	}
+
+	#[test]
+	fn delegation_is_limited_by_bonded_akt() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_err!(Akt::delegate(Origin::signed(ALICE), ISSUER, 100), "Account is not bonded");
+			assert_ok!(Akt::bond(Origin::signed(ALICE), 500));
+			assert_err!(Akt::delegate(Origin::signed(ALICE), ALICE, 100), "You can not delegate to yourself");
+
+			assert_ok!(Akt::delegate(Origin::signed(ALICE), ISSUER, 300));
+			assert_ok!(Akt::delegate(Origin::signed(ALICE), ISSUER, 300));
+			assert_err!(Akt::delegate(Origin::signed(ALICE), ISSUER, 1), "Nothing to delegate");
+			assert_eq!(Akt::delegation((ALICE, ISSUER)), 500);
+			assert_eq!(Akt::total_delegated(ISSUER), 500);
+			assert_eq!(Akt::delegated_to(ALICE), vec![ISSUER]);
+			assert_eq!(Akt::delegators_of(ISSUER), vec![ALICE]);
+			assert_eq!(Akt::ledger(ALICE), Some(ledger(500, 500, 500, vec![])));
+
+			// the delegated AKT can't be unbonded:
+			assert_err!(Akt::unbond(Origin::signed(ALICE), 1), "Nothing to unbond");
+		});
+	}
+
+	#[test]
+	fn held_delegation_can_not_be_undelegated() {
+		with_externalities(&mut new_test_ext(), || {
+			delegate(ALICE, 300);
+			<Akt as Backing<u64, H256, u64>>::hold(&ISSUER, 200);
+			assert_eq!(<Akt as Backing<u64, H256, u64>>::delegated(&ISSUER), 100);
+
+			assert_err!(
+				Akt::undelegate(Origin::signed(ALICE), ISSUER, 101),
+				"Delegated AKT are held by the stakes of the issuer"
+			);
+			assert_ok!(Akt::undelegate(Origin::signed(ALICE), ISSUER, 100));
+
+			<Akt as Backing<u64, H256, u64>>::release(&ISSUER, 200);
+			assert_ok!(Akt::undelegate(Origin::signed(ALICE), ISSUER, 1_000));
+			assert_err!(Akt::undelegate(Origin::signed(ALICE), ISSUER, 1), "Nothing to undelegate");
+			assert_eq!(Akt::total_delegated(ISSUER), 0);
+			assert!(Akt::delegated_to(ALICE).is_empty());
+			assert!(Akt::delegators_of(ISSUER).is_empty());
+			assert_eq!(Akt::ledger(ALICE), Some(ledger(300, 300, 0, vec![])));
+		});
+	}
+
+	#[test]
+	fn rewards_of_held_akt_are_shared_by_delegators() {
+		with_externalities(&mut new_test_ext(), || {
+			delegate(ALICE, 300);
+			delegate(BOB, 100);
+			assert_ok!(Akt::set_reward_rate(Origin::ROOT, None, 100_000));
+			assert_ok!(Akt::set_inflation_rewards(Origin::ROOT, true));
+
+			<Akt as OnPeriodPaid<u64, H256, u64>>::on_period_paid(H256::zero(), &ISSUER, 1_000, 400);
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT + 100);
+			assert_eq!(Akt::free_balance(&ALICE), ENDOWMENT + 30);
+			assert_eq!(Akt::free_balance(&BOB), ENDOWMENT + 10);
+		});
+	}
+
+	#[test]
+	fn breach_slashes_issuer_and_delegators_by_their_parts() {
+		with_externalities(&mut new_test_ext(), || {
+			delegate(ALICE, 300);
+			delegate(BOB, 100);
+			<SlashRate<Test>>::put(100_000);
+			<Akt as Backing<u64, H256, u64>>::hold(&ISSUER, 400);
+
+			assert_eq!(<Akt as Backing<u64, H256, u64>>::on_breach(H256::zero(), &ISSUER, 1_000, 400), (100, 40));
+			assert_eq!(Akt::free_balance(&ISSUER), ENDOWMENT - 100);
+			assert_eq!(Akt::free_balance(&ALICE), ENDOWMENT - 30);
+			assert_eq!(Akt::free_balance(&BOB), ENDOWMENT - 10);
+
+			// the slashed AKT leave the delegation and the bonded AKT:
+			assert_eq!(Akt::delegation((ALICE, ISSUER)), 270);
+			assert_eq!(Akt::total_delegated(ISSUER), 360);
+			assert_eq!(Akt::held_delegation(ISSUER), 360);
+			assert_eq!(Akt::ledger(ALICE), Some(ledger(270, 270, 270, vec![])));
+			assert_eq!(Akt::total_issuance(), 3 * ENDOWMENT - 140);
+		});
+	}
+
+	#[test]
+	fn share_is_proportional_to_part() {
+		assert_eq!(share(1_000u64, 1, 0), 0);
+		assert_eq!(share(1_000u64, 1, 4), 250);
+		assert_eq!(share(1_000u64, 1, 3), 333);
+		assert_eq!(share(1_000u64, 4, 4), 1_000);
+		// no overflow of the large parts:
+		assert_eq!(share(1_000u64, u64::max_value() / 2, u64::max_value()), 500);
+	}
+}
@@ -0,0 +1,30 @@
+//! Runtime API to query AKT staking ledgers.
+
+use rstd::prelude::*;
+use parity_codec::Codec;
+use client::decl_runtime_apis;
+
+use crate::stake::{IssuanceSchedule, ScheduleId, StakingLedger};
+
+
+decl_runtime_apis! {
+	/// The API to query the `Stake` module.
+	pub trait StakeApi<AccountId, Balance, BlockNumber> where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Returns the bonded AKT of the account.
+		fn ledger(who: AccountId) -> Option<StakingLedger<Balance, BlockNumber>>;
+		/// Number of blocks the unbonded funds stay locked.
+		fn unbonding_duration() -> BlockNumber;
+		/// Returns (total issuance, scheduled issuance, max issuance) of AKT.
+		fn issuance() -> (Balance, Balance, Balance);
+		/// Returns the issuance schedule with specified id.
+		fn schedule(id: ScheduleId) -> Option<IssuanceSchedule<AccountId, Balance, BlockNumber>>;
+		/// Issuers the account delegates to: (issuer, amount).
+		fn delegations_of(delegator: AccountId) -> Vec<(AccountId, Balance)>;
+		/// Delegators of the issuer: (delegator, amount).
+		fn delegators_of(issuer: AccountId) -> Vec<(AccountId, Balance)>;
+	}
+}
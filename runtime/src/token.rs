@@ -0,0 +1,136 @@
+//! Fungible assets identified by `AssetId`.
+//!
+//! Any account can issue a new asset and becomes its issuer, only the issuer can mint more of it.
+//! `NATIVE_ASSET` stands for the native balance, so other modules can pay in any asset by its id.
+
+use rstd::result;
+use support::{StorageMap, StorageValue};
+use support::dispatch::Result;
+use support::{decl_module, decl_storage, decl_event};
+use support::ensure;
+use support::traits::Currency;
+use system::ensure_signed;
+use runtime_primitives::traits::{CheckedAdd, CheckedSub, Zero};
+
+
+/// Id of the asset.
+pub type AssetId = u32;
+
+/// Id of the native balance.
+pub const NATIVE_ASSET: AssetId = 0;
+
+
+pub trait Trait: balances::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Token {
+		/// (asset_id, account) -> balance of the asset
+		Balances get(balance_of): map (AssetId, T::AccountId) => T::Balance;
+		/// asset_id -> total supply of the asset
+		TotalSupply get(total_supply): map AssetId => T::Balance;
+		/// asset_id -> account which can mint the asset
+		Issuer get(issuer_of): map AssetId => Option<T::AccountId>;
+		/// Id of the last issued asset, zero is the native balance.
+		LastAssetId get(last_asset_id): AssetId;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event<T>() = default;
+
+		/// Issue a new asset with the `total` supply owned by the sender.
+		fn issue(origin, total: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+
+			let id = Self::last_asset_id().checked_add(1).ok_or("No free asset id")?;
+			<LastAssetId<T>>::put(id);
+			<Issuer<T>>::insert(id, sender.clone());
+			<Balances<T>>::insert((id, sender.clone()), total);
+			<TotalSupply<T>>::insert(id, total);
+
+			Self::deposit_event(RawEvent::Issued(id, sender, total));
+
+			Ok(())
+		}
+
+		/// Mint `amount` of the asset to the account. Only issuer of the asset can do it.
+		fn mint(origin, id: AssetId, to: T::AccountId, amount: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			ensure!(Self::issuer_of(id) == Some(sender), "You are not the issuer of this asset");
+
+			let total = Self::total_supply(id).checked_add(&amount).ok_or("Overflow of the asset supply")?;
+			let balance = Self::balance_of((id, to.clone())).checked_add(&amount).ok_or("Overflow of the asset balance")?;
+			<TotalSupply<T>>::insert(id, total);
+			<Balances<T>>::insert((id, to.clone()), balance);
+
+			Self::deposit_event(RawEvent::Minted(id, to, amount));
+
+			Ok(())
+		}
+
+		/// Transfer `amount` of the asset, including the native one.
+		fn transfer(origin, id: AssetId, to: T::AccountId, amount: T::Balance) -> Result {
+			let sender = ensure_signed(origin)?;
+			ensure!(!amount.is_zero(), "Transfer amount can not be zero");
+
+			Self::transfer_asset(id, &sender, &to, amount)?;
+
+			Self::deposit_event(RawEvent::Transferred(id, sender, to, amount));
+
+			Ok(())
+		}
+	}
+}
+
+
+decl_event!(
+	pub enum Event<T>
+		where AccountId = <T as system::Trait>::AccountId,
+		      Balance = <T as balances::Trait>::Balance,
+	{
+		/// (asset_id:AssetId, issuer:AccountId, total:Balance)
+		Issued(AssetId, AccountId, Balance),
+		/// (asset_id:AssetId, to:AccountId, amount:Balance)
+		Minted(AssetId, AccountId, Balance),
+		/// (asset_id:AssetId, from:AccountId, to:AccountId, amount:Balance)
+		Transferred(AssetId, AccountId, AccountId, Balance),
+	}
+);
+
+
+impl<T: Trait> Module<T> {
+	/// Whether the asset is issued or is the native one.
+	pub fn asset_exists(id: AssetId) -> bool {
+		id == NATIVE_ASSET || <Issuer<T>>::exists(id)
+	}
+
+	/// Balance of the asset, including the native one.
+	pub fn free_balance_of(id: AssetId, who: &T::AccountId) -> T::Balance {
+		if id == NATIVE_ASSET {
+			<balances::Module<T> as Currency<T::AccountId>>::free_balance(who)
+		} else {
+			Self::balance_of((id, who.clone()))
+		}
+	}
+
+	/// Move `amount` of the asset, including the native one.
+	pub fn transfer_asset(id: AssetId, from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> result::Result<(), &'static str> {
+		if id == NATIVE_ASSET {
+			return <balances::Module<T> as Currency<T::AccountId>>::transfer(from, to, amount);
+		}
+
+		ensure!(<Issuer<T>>::exists(id), "This asset does not exist");
+		let from_balance = Self::balance_of((id, from.clone())).checked_sub(&amount).ok_or("Asset balance too low to send value")?;
+		if from != to {
+			let to_balance = Self::balance_of((id, to.clone())).checked_add(&amount).ok_or("Overflow of the asset balance")?;
+			<Balances<T>>::insert((id, from.clone()), from_balance);
+			<Balances<T>>::insert((id, to.clone()), to_balance);
+		}
+
+		Ok(())
+	}
+}
@@ -11,24 +11,46 @@ use akropolis_runtime::{/* AuthorityId, */
                     StakingConfig,
                     StakerStatus,
                     SudoConfig,
+                    TreasuryConfig,
                     IndicesConfig,
-                    // AssetsConfig
-                    Perbill};
+                    CashflowConfig,
+                    StakeConfig,
+                    Parameters,
+                    Schedule,
+                    Start,
+                    Clock,
+                    Perbill,
+                    Permill};
 use substrate_service;
 use telemetry::TelemetryEndpoints;
 // use sr25519::Public as AccountId;
 use ed25519::Public as AuthorityId;
 
+use serde::Deserialize;
 use serde_json::json;
 
 
 use crate::consts::{CHAIN_NAME, CHAIN_ID, CHAIN_ID_SHORT};
 use crate::consts::{CHAIN_TESTNET_NAME, CHAIN_TESTNET_ID, CHAIN_TESTNET_ID_SHORT};
+use crate::consts::{CHAIN_DEV_NAME, CHAIN_DEV_ID, CHAIN_LOCAL_NAME, CHAIN_LOCAL_ID, CHAIN_LOCAL_ID_SHORT};
 
 // Note this is the URL for the telemetry server
 const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
 const STAGING_TELEMETRY_LEVEL: u8 = 1;
-const DEFAULT_PROTOCOL_ID: &str = "dot"; // sup?
+pub(crate) const DEFAULT_PROTOCOL_ID: &str = "dot"; // sup?
+/// Set to seed development chains with C2FC buckets and promises.
+const C2FC_FIXTURES_ENV: &str = "AKROPOLIS_C2FC_FIXTURES";
+
+const MILLICENTS: u128 = 1_000_000_000;
+const CENTS: u128 = 1_000 * MILLICENTS; // assume this is worth about a cent.
+const DOLLARS: u128 = 100 * CENTS;
+
+const SECS_PER_BLOCK: u64 = 6;
+const MINUTES: u64 = 60 / SECS_PER_BLOCK;
+const HOURS: u64 = 60 * MINUTES;
+
+/// Default balance of endowed accounts.
+pub(crate) const ENDOWMENT: u128 = 10_000_000 * DOLLARS;
 
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = substrate_service::ChainSpec<GenesisConfig>;
@@ -48,30 +70,108 @@ pub enum Alternative {
 	Akropolis,
 }
 
-fn authority_key(s: &str) -> AuthorityId {
+pub(crate) fn authority_key(s: &str) -> AuthorityId {
 	ed25519::Pair::from_string(&format!("//{}", s), None).expect("static values are valid; qed")
 	                                                     .public()
 }
 
-fn account_key(s: &str) -> AccountId {
+pub(crate) fn account_key(s: &str) -> AccountId {
 	sr25519::Pair::from_string(&format!("//{}", s), None).expect("static values are valid; qed")
 	                                                     .public()
 }
 
+/// Stash, controller and session key of the well-known authority, e.g. "Alice".
+pub(crate) fn get_authority_keys_from_seed(s: &str) -> (AccountId, AccountId, AuthorityId) {
+	(account_key(&format!("{}//stash", s)), account_key(s), authority_key(s))
+}
+
+/// Well-known development accounts with their stashes.
+fn well_known_accounts() -> Vec<AccountId> {
+	["Alice", "Bob", "Charlie", "Dave", "Eve", "Ferdie"].iter()
+	                                                    .flat_map(|s| vec![account_key(s), account_key(&format!("{}//stash", s))])
+	                                                    .collect()
+}
+
+/// Id of the C2FC fixture.
+fn fixture_id(s: &str) -> akropolis_runtime::Hash {
+	primitives::blake2_256(s.as_bytes()).into()
+}
+
+/// Default market parameters of the C2FC.
+pub(crate) fn c2fc_parameters() -> Parameters<u128, u64> {
+	Parameters { min_period: 10,
+	             max_value: 0,
+	             max_promises_per_account: 100,
+	             creation_fee: 0,
+	             grace_period: 10,
+	             storage_deposit: DOLLARS,
+	             sale_fee: Permill::from_percent(1),
+	             payment_fee: Permill::from_millionths(1_000),
+	             max_installments: 120,
+	             max_royalty: Permill::from_percent(10) }
+}
+
+/// C2FC section of the genesis, optionally seeded with fixtures of well-known accounts:
+/// - Alice's bucket with accepted promise of Bob, staked by Bob
+/// - free promises of Bob and Charlie
+/// - Dave's empty bucket for sale
+pub(crate) fn c2fc_genesis(fixtures: bool) -> CashflowConfig {
+	if !fixtures {
+		return CashflowConfig { parameters: c2fc_parameters(),
+		                        buckets: vec![],
+		                        promises: vec![],
+		                        accepted: vec![],
+		                        stakes: vec![] };
+	}
+
+	CashflowConfig { parameters: c2fc_parameters(),
+	                 buckets: vec![(account_key("Alice"), fixture_id("bucket/alice"), 0),
+	                               (account_key("Dave"), fixture_id("bucket/dave"), 100 * DOLLARS),],
+	                 promises: vec![(account_key("Bob"),
+	                                 fixture_id("promise/bob/1"),
+	                                 10 * DOLLARS,
+	                                 100,
+	                                 0,
+	                                 Schedule::Constant,
+	                                 Start::Immediate,
+	                                 Clock::Blocks),
+	                                (account_key("Bob"),
+	                                 fixture_id("promise/bob/2"),
+	                                 5 * DOLLARS,
+	                                 200,
+	                                 10_000,
+	                                 Schedule::StepUp(DOLLARS),
+	                                 Start::Delay(100),
+	                                 Clock::Blocks),
+	                                (account_key("Charlie"),
+	                                 fixture_id("promise/charlie/1"),
+	                                 1_000 * DOLLARS,
+	                                 1,
+	                                 0,
+	                                 Schedule::Constant,
+	                                 Start::Immediate,
+	                                 Clock::Months),],
+	                 accepted: vec![(fixture_id("promise/bob/1"), fixture_id("bucket/alice"))],
+	                 stakes: vec![(fixture_id("promise/bob/1"), 1_000 * DOLLARS)] }
+}
+
+fn c2fc_fixtures_enabled() -> bool {
+	std::env::var(C2FC_FIXTURES_ENV).map(|v| !v.is_empty() && v != "0").unwrap_or(false)
+}
+
 /// AKT token properties.
-fn token_props() -> substrate_service::Properties {
+pub(crate) fn token_props() -> substrate_service::Properties {
 	json!({"tokenDecimals": 18, "tokenSymbol": "AKT"}).as_object()
 	                                                  .unwrap()
 	                                                  .clone()
 }
 
+/// The Akropolis testnet validator, further authorities join through staking.
 fn get_initial_authorities() -> Vec<(AccountId, AccountId, AuthorityId)> {
 	// 5EE4p6upP21hxqrKZGH1vPr4azoN63eYQT5kszmbKVvK61NL: 5f9c380ad795be476350d9b31f5ad771abfe728d918b7e35021259f66da17470
 	// 5FVEDPNip5otFuo47X4JYkfZxUPezf8QuZaYGbHWRidCmgru: 9768c811cf000ce59faec3de5a915193ca87e90224142bbc4117a7201e123ee6
 	// 5EJEZLV9UNxv6HpVYxDwfQf7oDtamRU4dXG9gqx1XJ14MzWK: 62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8
 
-	// TODO: XXX: FIX KEYS
-
 	let aira_auth: AuthorityId =
 		hex!["5f9c380ad795be476350d9b31f5ad771abfe728d918b7e35021259f66da17470"].unchecked_into();
 	let aira_stash: AccountId =
@@ -79,15 +179,7 @@ fn get_initial_authorities() -> Vec<(AccountId, AccountId, AuthorityId)> {
 	let aira_control: AccountId =
 		hex!["62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8"].unchecked_into();
 
-	let akru_auth: AuthorityId =
-		hex!["5f9c380ad795be476350d9b31f5ad771abfe728d918b7e35021259f66da17470"].unchecked_into();
-	let akru_stash: AccountId =
-		hex!["9768c811cf000ce59faec3de5a915193ca87e90224142bbc4117a7201e123ee6"].unchecked_into();
-	let akru_control: AccountId =
-		hex!["62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8"].unchecked_into();
-
-	vec![(aira_stash, aira_control.clone(), aira_auth),
-	     (akru_stash, akru_control.clone(), akru_auth),]
+	vec![(aira_stash, aira_control, aira_auth)]
 }
 
 impl Alternative {
@@ -121,8 +213,10 @@ impl Alternative {
 					                        testnet_genesis(
 					                                        // initial_authorities.iter().map(|id| id.clone()).collect(),
 					                                        initial_authorities,
-					                                        endowed_accounts.iter().map(|id| id.clone()).collect(),
+					                                        endowed_accounts.into_iter().map(|id| (id, ENDOWMENT)).collect(),
 					                                        root_key,
+					                                        StakingParams::default(),
+					                                        c2fc_genesis(false),
 					)
 					                       },
 				                        vec![],
@@ -140,35 +234,104 @@ impl Alternative {
 				)
 			},
 
-			_ => panic!("noooooo"),
+			Alternative::Development => {
+				ChainSpec::from_genesis(CHAIN_DEV_NAME,
+				                        CHAIN_DEV_ID,
+				                        || {
+					                        testnet_genesis(vec![get_authority_keys_from_seed("Alice")],
+					                                        well_known_accounts().into_iter().map(|id| (id, ENDOWMENT)).collect(),
+					                                        account_key("Alice"),
+					                                        StakingParams::default(),
+					                                        c2fc_genesis(c2fc_fixtures_enabled()))
+					                       },
+				                        vec![],
+				                        None,
+				                        Some(DEFAULT_PROTOCOL_ID),
+				                        None,
+				                        Some(token_props()))
+			},
+
+			Alternative::LocalTestnet => {
+				ChainSpec::from_genesis(CHAIN_LOCAL_NAME,
+				                        CHAIN_LOCAL_ID,
+				                        || {
+					                        testnet_genesis(vec![get_authority_keys_from_seed("Alice"),
+					                                             get_authority_keys_from_seed("Bob")],
+					                                        well_known_accounts().into_iter().map(|id| (id, ENDOWMENT)).collect(),
+					                                        account_key("Alice"),
+					                                        StakingParams::default(),
+					                                        c2fc_genesis(c2fc_fixtures_enabled()))
+					                       },
+				                        vec![],
+				                        None,
+				                        Some(DEFAULT_PROTOCOL_ID),
+				                        None,
+				                        Some(token_props()))
+			},
 		})
 	}
 
 	pub(crate) fn from(s: &str) -> Option<Self> {
 		match s {
-			"dev" => Some(Alternative::Development),
+			CHAIN_DEV_ID => Some(Alternative::Development),
 			CHAIN_TESTNET_ID_SHORT | CHAIN_TESTNET_ID => Some(Alternative::Akropolis),
-			"" | "local" => Some(Alternative::LocalTestnet),
+			"" | CHAIN_LOCAL_ID_SHORT | CHAIN_LOCAL_ID => Some(Alternative::LocalTestnet),
 			_ => None,
 		}
 	}
 }
 
-fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>,
-                   endowed_accounts: Vec<AccountId>, root_key: AccountId)
-                   -> GenesisConfig
-{
-	const MILLICENTS: u128 = 1_000_000_000;
-	const CENTS: u128 = 1_000 * MILLICENTS; // assume this is worth about a cent.
-	const DOLLARS: u128 = 100 * CENTS;
+/// Session and staking parameters of the genesis.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct StakingParams {
+	pub validator_count: u32,
+	/// Defaults to the number of initial authorities but at most 2.
+	pub minimum_validator_count: Option<u32>,
+	pub session_length: u64,
+	pub sessions_per_era: u64,
+	pub bonding_duration: u64,
+	/// Balance bonded by the stash of every initial authority.
+	#[serde(deserialize_with = "crate::custom_spec::balance")]
+	pub stash: u128,
+	/// Number of blocks the unbonded AKT stay locked.
+	pub unbonding_duration: u64,
+	/// Cap of the AKT issuance, endowed accounts get the same amount of AKT as of the native balance.
+	#[serde(deserialize_with = "crate::custom_spec::balance")]
+	pub max_issuance: u128,
+	/// Number of blocks of the AKT reward era.
+	pub reward_era_length: u64,
+	/// AKT reward for the paid period of the staked promise, in millionths of the stake.
+	pub reward_rate: u32,
+	/// Whether the AKT rewards are minted when the reward pool is empty.
+	pub inflation_rewards: bool,
+	/// Part of the promise stake slashed on breach, in millionths of the stake.
+	pub slash_rate: u32,
+}
 
-	const SECS_PER_BLOCK: u64 = 6;
-	const MINUTES: u64 = 60 / SECS_PER_BLOCK;
-	const HOURS: u64 = MINUTES * 60;
-	const DAYS: u64 = HOURS * 24;
+impl Default for StakingParams {
+	fn default() -> Self {
+		StakingParams { validator_count: 7,
+		                minimum_validator_count: None,
+		                session_length: 15,
+		                sessions_per_era: 10,
+		                bonding_duration: 10 * MINUTES,
+		                stash: 100 * DOLLARS,
+		                unbonding_duration: 24 * HOURS,
+		                max_issuance: 1_000_000_000 * DOLLARS,
+		                reward_era_length: 24 * HOURS,
+		                reward_rate: 1_000,
+		                inflation_rewards: true,
+		                slash_rate: 100_000 }
+	}
+}
 
-	const ENDOWMENT: u128 = 10_000_000 * DOLLARS;
-	const STASH: u128 = 100 * DOLLARS;
+/// Genesis of the testnet with the given balances of endowed accounts.
+pub(crate) fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>,
+                              endowed_accounts: Vec<(AccountId, u128)>, root_key: AccountId,
+                              staking: StakingParams, c2fc: CashflowConfig)
+                              -> GenesisConfig
+{
 
 	GenesisConfig { consensus: Some(ConsensusConfig { code: include_bytes!(
 		"../runtime/wasm/target/wasm32-unknown-unknown/release/akropolis_runtime_wasm.compact.wasm"
@@ -181,7 +344,7 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>
 			// minimum_period: 15, // 30 second block time.
 			minimum_period: 5, // 10 second block time.
 		}),
-	                indices: Some(IndicesConfig { ids: endowed_accounts.clone() }),
+	                indices: Some(IndicesConfig { ids: endowed_accounts.iter().map(|x| x.0.clone()).collect() }),
 	                balances: Some(BalancesConfig { // transaction_base_fee: 1 * CENTS,
 	                                                // transaction_byte_fee: 10 * MILLICENTS,
 	                                                transaction_base_fee: 0 * CENTS,
@@ -190,23 +353,21 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>
 	                                                // transfer_fee: 1 * CENTS, creation_fee: 1 * CENTS,
 	                                                transfer_fee: 0 * CENTS,
 	                                                creation_fee: 0 * CENTS,
-	                                                balances: endowed_accounts.iter()
-	                                                                          .cloned()
-	                                                                          .map(|k| (k, ENDOWMENT))
-	                                                                          .collect(),
+	                                                balances: endowed_accounts.clone(),
 	                                                vesting: vec![] }),
 	                session: Some(SessionConfig { validators: initial_authorities.iter()
 	                                                                             .map(|x| x.1.clone())
 	                                                                             .collect(),
-	                                              session_length: 15,
+	                                              session_length: staking.session_length,
 	                                              keys: initial_authorities.iter()
 	                                                                       .map(|x| (x.1.clone(), x.2.clone()))
 	                                                                       .collect::<Vec<_>>() }),
 	                staking: Some(StakingConfig { current_era: 0,
-	                                              minimum_validator_count: 2,
-	                                              validator_count: 7,
-	                                              sessions_per_era: 10,
-	                                              bonding_duration: 10 * MINUTES,
+	                                              minimum_validator_count: staking.minimum_validator_count
+	                                                                              .unwrap_or(initial_authorities.len().min(2) as u32),
+	                                              validator_count: staking.validator_count,
+	                                              sessions_per_era: staking.sessions_per_era,
+	                                              bonding_duration: staking.bonding_duration,
 	                                              current_session_reward: 0,
 	                                              session_reward: Perbill::from_millionths(200_000),
 	                                              offline_slash: Perbill::from_millionths(1_000_000),
@@ -215,7 +376,7 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>
 	                                                                          .map(|x| {
 		                                                                          (x.0.clone(),
 		                                                                           x.1.clone(),
-		                                                                           STASH,
+		                                                                           staking.stash,
 		                                                                           StakerStatus::Validator)
 		                                                                         })
 	                                                                          .collect(),
@@ -226,6 +387,18 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>
 	                                              authorities: initial_authorities.iter()
 	                                                                              .map(|x| (x.2.clone(), 1))
 	                                                                              .collect() }),
-	                // TODO: assets: Some(AssetsConfig{}),
-	                sudo: Some(SudoConfig { key: root_key }) }
+	                sudo: Some(SudoConfig { key: root_key }),
+	                treasury: Some(TreasuryConfig { proposal_bond: Permill::from_percent(5),
+	                                                proposal_bond_minimum: 1 * DOLLARS,
+	                                                spend_period: 24 * HOURS,
+	                                                burn: Permill::from_percent(50) }),
+	                c2fc: Some(c2fc),
+	                // endowed accounts get the same amount of AKT:
+	                stake: Some(StakeConfig { balances: endowed_accounts,
+	                                          unbonding_duration: staking.unbonding_duration,
+	                                          max_issuance: staking.max_issuance,
+	                                          era_length: staking.reward_era_length,
+	                                          reward_rate: staking.reward_rate,
+	                                          inflation_rewards: staking.inflation_rewards,
+	                                          slash_rate: staking.slash_rate }) }
 }
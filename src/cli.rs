@@ -3,12 +3,45 @@ use futures::{future, Future, sync::oneshot};
 use std::cell::RefCell;
 use tokio::runtime::Runtime;
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{informant, parse_and_execute};
 use substrate_service::{ServiceFactory, Roles as ServiceRoles};
 use crate::consts::NODE_NAME_TEL;
 use crate::chain_spec;
+use crate::custom_spec::C2fcSubcommands;
+use crate::rpc;
 use std::ops::Deref;
+use std::net::SocketAddr;
 use log::info;
+use structopt::StructOpt;
+
+/// Default port of the `c2fc_*` JSON-RPC HTTP server.
+const C2FC_RPC_PORT: u16 = 9955;
+/// Default port of the `c2fc_*` JSON-RPC websocket server.
+const C2FC_WS_PORT: u16 = 9956;
+
+/// Additional parameters of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct C2fcParams {
+	/// Specify the `c2fc_*` JSON-RPC HTTP server TCP port.
+	#[structopt(long = "c2fc-rpc-port", value_name = "PORT")]
+	pub c2fc_rpc_port: Option<u16>,
+
+	/// Specify the `c2fc_*` JSON-RPC websocket server TCP port.
+	#[structopt(long = "c2fc-ws-port", value_name = "PORT")]
+	pub c2fc_ws_port: Option<u16>,
+}
+
+impl C2fcParams {
+	/// Listens on the interface of the node's HTTP RPC server, `None` if the node's server is disabled.
+	fn c2fc_rpc_addr(&self, node_rpc: Option<SocketAddr>) -> Option<SocketAddr> {
+		node_rpc.map(|addr| SocketAddr::new(addr.ip(), self.c2fc_rpc_port.unwrap_or(C2FC_RPC_PORT)))
+	}
+
+	/// Listens on the interface of the node's websocket RPC server, `None` if the node's server is disabled.
+	fn c2fc_ws_addr(&self, node_ws: Option<SocketAddr>) -> Option<SocketAddr> {
+		node_ws.map(|addr| SocketAddr::new(addr.ip(), self.c2fc_ws_port.unwrap_or(C2FC_WS_PORT)))
+	}
+}
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
@@ -16,9 +49,9 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+	parse_and_execute::<service::Factory, C2fcSubcommands, C2fcParams, _, _, _, _, _>(
 		load_spec, &version, NODE_NAME_TEL, args, exit,
-	 	|exit, _custom_args, config| {
+	 	|exit, custom_args, config| {
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2019", version.author);
@@ -33,14 +66,32 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 				 	service::Factory::new_light(config, executor).map_err(|e| format!("{:?}", e))?,
 					exit
 				),
-				_ => run_until_exit(
-					runtime,
-					service::Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
-					exit
-				),
+				_ => {
+					// the servers follow `--rpc-external`, `--ws-external` and `--rpc-cors` of the node:
+					let rpc_addr = custom_args.c2fc_rpc_addr(config.rpc_http);
+					let ws_addr = custom_args.c2fc_ws_addr(config.rpc_ws);
+					let rpc_cors = config.rpc_cors.clone();
+
+					let service = service::Factory::new_full(config, executor.clone()).map_err(|e| format!("{:?}", e))?;
+					// keep the servers alive until exit:
+					let _c2fc_rpc = match rpc_addr {
+						Some(addr) => Some(rpc::start_http(&addr, rpc_cors.as_ref(), service.client())
+							.map_err(|e| format!("Unable to start C2FC RPC server: {:?}", e))?),
+						None => None,
+					};
+					let _c2fc_ws = match ws_addr {
+						Some(addr) => Some(rpc::start_ws(&addr, rpc_cors.as_ref(), service.client(), executor.clone())
+							.map_err(|e| format!("Unable to start C2FC WS server: {:?}", e))?),
+						None => None,
+					};
+					run_until_exit(runtime, service, exit)
+				},
 			}.map_err(|e| format!("{:?}", e))
 		}
-	).map_err(Into::into).map(|_| ())
+	).map_err(Into::into).and_then(|custom| match custom {
+		Some(cmd) => cmd.run().map_err(Into::into),
+		None => Ok(()),
+	})
 }
 
 fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
@@ -11,6 +11,13 @@ pub const CHAIN_TESTNET_NAME: &str = "Akropolis";
 pub const CHAIN_TESTNET_ID: &str = "akropolis-testnet";
 pub const CHAIN_TESTNET_ID_SHORT: &str = "akro-test";
 
+pub const CHAIN_DEV_NAME: &str = "Development";
+pub const CHAIN_DEV_ID: &str = "dev";
+
+pub const CHAIN_LOCAL_NAME: &str = "Local Testnet";
+pub const CHAIN_LOCAL_ID: &str = "local_testnet";
+pub const CHAIN_LOCAL_ID_SHORT: &str = "local";
+
 pub const AUTHOR_NAME: &str = "Akropolis Team";
 pub const DESCRIPTION: &str = "Akropolis Substrate Node (C2FC)";
 pub const SUPPORT_URL: &str = "support@akropolis.io";
@@ -0,0 +1,263 @@
+//! Chain specification built from a declarative TOML or JSON file.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+
+use primitives::{ed25519, sr25519, Pair};
+use primitives::crypto::{Ss58Codec, UncheckedFrom};
+use akropolis_runtime::{AccountId, CashflowConfig};
+use ed25519::Public as AuthorityId;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use structopt::StructOpt;
+
+use crate::chain_spec::{self, ChainSpec, StakingParams, DEFAULT_PROTOCOL_ID, ENDOWMENT};
+
+/// Custom subcommands of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub enum C2fcSubcommands {
+	/// Build a chain specification from a TOML or JSON config file.
+	#[structopt(name = "build-custom-spec")]
+	BuildCustomSpec(BuildCustomSpecCmd),
+}
+
+impl substrate_cli::GetLogFilter for C2fcSubcommands {
+	fn get_log_filter(&self) -> Option<String> {
+		None
+	}
+}
+
+impl C2fcSubcommands {
+	/// Run the subcommand.
+	pub fn run(self) -> Result<(), String> {
+		match self {
+			C2fcSubcommands::BuildCustomSpec(cmd) => cmd.run(),
+		}
+	}
+}
+
+/// The `build-custom-spec` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct BuildCustomSpecCmd {
+	/// Path to the config file, `.toml` or `.json`.
+	#[structopt(parse(from_os_str), value_name = "CONFIG")]
+	pub config: PathBuf,
+
+	/// Force raw genesis storage output.
+	#[structopt(long = "raw")]
+	pub raw: bool,
+}
+
+impl BuildCustomSpecCmd {
+	fn run(&self) -> Result<(), String> {
+		let config = SpecConfig::load(&self.config)?;
+		let spec = config.build()?;
+		// building raw storage runs the genesis of every module and catches invalid C2FC fixtures:
+		let raw = spec.to_json(true)?;
+		let json = if self.raw { raw } else { spec.to_json(false)? };
+		print!("{}", json);
+		Ok(())
+	}
+}
+
+
+/// Chain specification config file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecConfig {
+	name: String,
+	id: String,
+	#[serde(default)]
+	protocol_id: Option<String>,
+	#[serde(default)]
+	boot_nodes: Vec<String>,
+	#[serde(default)]
+	telemetry: Vec<TelemetryConfig>,
+	/// Defaults to the AKT token properties.
+	#[serde(default)]
+	properties: Option<substrate_service::Properties>,
+	authorities: Vec<AuthorityConfig>,
+	#[serde(default)]
+	endowed: Vec<EndowedConfig>,
+	sudo: String,
+	#[serde(default)]
+	staking: StakingParams,
+	#[serde(default)]
+	c2fc: Option<CashflowConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TelemetryConfig {
+	url: String,
+	#[serde(default)]
+	level: u8,
+}
+
+/// Authority given by the well-known seed (e.g. "Alice") or by its keys.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AuthorityConfig {
+	Seed { seed: String },
+	Keys { stash: String, controller: String, session: String },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EndowedConfig {
+	account: String,
+	#[serde(default = "default_endowment", deserialize_with = "balance")]
+	balance: u128,
+}
+
+fn default_endowment() -> u128 {
+	ENDOWMENT
+}
+
+/// Balance given as an integer or as a decimal string, TOML integers can't hold large balances.
+pub(crate) fn balance<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Repr {
+		Int(u64),
+		Str(String),
+	}
+
+	match Repr::deserialize(deserializer)? {
+		Repr::Int(value) => Ok(value as u128),
+		Repr::Str(value) => value.parse().map_err(serde::de::Error::custom),
+	}
+}
+
+/// Public key given as a secret URI (e.g. "//Alice"), `0x`-prefixed hex or SS58 address.
+fn parse_public<P>(s: &str) -> Result<P::Public, String>
+	where P: Pair,
+	      P::Public: Ss58Codec + UncheckedFrom<[u8; 32]>
+{
+	if s.starts_with("//") {
+		P::from_string(s, None).map(|pair| pair.public())
+		                       .map_err(|e| format!("Invalid secret URI {}: {:?}", s, e))
+	} else if s.starts_with("0x") {
+		let bytes = hex::decode(&s[2..]).map_err(|e| format!("Invalid hex key {}: {}", s, e))?;
+		if bytes.len() != 32 {
+			return Err(format!("Invalid hex key {}: expected 32 bytes", s));
+		}
+		let mut raw = [0u8; 32];
+		raw.copy_from_slice(&bytes);
+		Ok(P::Public::unchecked_from(raw))
+	} else {
+		P::Public::from_ss58check(s).map_err(|e| format!("Invalid address {}: {:?}", s, e))
+	}
+}
+
+fn ensure_unique<T: Eq + std::hash::Hash + Display>(seen: &mut HashSet<T>, key: T, what: &str) -> Result<(), String> {
+	if seen.contains(&key) {
+		return Err(format!("Duplicate {} {}", what, key));
+	}
+	seen.insert(key);
+	Ok(())
+}
+
+impl SpecConfig {
+	/// Read the config file, the format is chosen by the extension.
+	pub fn load(path: &PathBuf) -> Result<Self, String> {
+		let content = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("toml") => toml::from_str(&content).map_err(|e| format!("Invalid config {}: {}", path.display(), e)),
+			Some("json") => serde_json::from_str(&content).map_err(|e| format!("Invalid config {}: {}", path.display(), e)),
+			_ => Err(format!("Unknown config format {}, expected .toml or .json", path.display())),
+		}
+	}
+
+	fn authorities(&self) -> Result<Vec<(AccountId, AccountId, AuthorityId)>, String> {
+		let authorities = self.authorities.iter().map(|authority| match authority {
+			AuthorityConfig::Seed { seed } => Ok(chain_spec::get_authority_keys_from_seed(seed)),
+			AuthorityConfig::Keys { stash, controller, session } => {
+				Ok((parse_public::<sr25519::Pair>(stash)?,
+				    parse_public::<sr25519::Pair>(controller)?,
+				    parse_public::<ed25519::Pair>(session)?))
+			},
+		}).collect::<Result<Vec<_>, String>>()?;
+
+		let mut accounts = HashSet::new();
+		let mut sessions = HashSet::new();
+		for (stash, controller, session) in &authorities {
+			ensure_unique(&mut accounts, stash.to_ss58check(), "authority stash")?;
+			if controller != stash {
+				ensure_unique(&mut accounts, controller.to_ss58check(), "authority controller")?;
+			}
+			ensure_unique(&mut sessions, session.to_ss58check(), "authority session key")?;
+		}
+
+		Ok(authorities)
+	}
+
+	fn endowed(&self) -> Result<Vec<(AccountId, u128)>, String> {
+		let mut seen = HashSet::new();
+		self.endowed.iter().map(|endowed| {
+			let account = parse_public::<sr25519::Pair>(&endowed.account)?;
+			ensure_unique(&mut seen, account.to_ss58check(), "endowed account")?;
+			Ok((account, endowed.balance))
+		}).collect()
+	}
+
+	/// Validate the config and build the chain specification.
+	pub fn build(self) -> Result<ChainSpec, String> {
+		if self.name.is_empty() || self.id.is_empty() {
+			return Err("Chain name and id can not be empty".into());
+		}
+
+		let authorities = self.authorities()?;
+		if authorities.is_empty() {
+			return Err("At least one authority is required".into());
+		}
+
+		let endowed = self.endowed()?;
+		for (stash, ..) in &authorities {
+			let funded = endowed.iter().any(|(account, balance)| account == stash && *balance >= self.staking.stash);
+			if !funded {
+				return Err(format!("Authority stash {} must be endowed with at least {}", stash.to_ss58check(), self.staking.stash));
+			}
+		}
+
+		let akt = endowed.iter().try_fold(0u128, |acc, (_, balance)| acc.checked_add(*balance));
+		if akt.map(|akt| akt > self.staking.max_issuance).unwrap_or(true) {
+			return Err(format!("Endowed AKT exceed the max issuance {}", self.staking.max_issuance));
+		}
+
+		if self.staking.reward_rate > akropolis_runtime::RATE_DENOMINATOR {
+			return Err(format!("Reward rate {} exceeds {}", self.staking.reward_rate, akropolis_runtime::RATE_DENOMINATOR));
+		}
+		if self.staking.slash_rate > akropolis_runtime::RATE_DENOMINATOR {
+			return Err(format!("Slash rate {} exceeds {}", self.staking.slash_rate, akropolis_runtime::RATE_DENOMINATOR));
+		}
+
+		let min_validators = self.staking.minimum_validator_count.unwrap_or(0);
+		if min_validators as usize > authorities.len() || min_validators > self.staking.validator_count {
+			return Err(format!("Minimum validator count {} exceeds the number of authorities or validator count", min_validators));
+		}
+
+		let sudo = parse_public::<sr25519::Pair>(&self.sudo)?;
+		let c2fc = self.c2fc.unwrap_or_else(|| chain_spec::c2fc_genesis(false));
+		let genesis = chain_spec::testnet_genesis(authorities, endowed, sudo, self.staking, c2fc);
+
+		let telemetry = self.telemetry.iter().map(|t| json!([t.url, t.level])).collect::<Vec<_>>();
+		let spec = json!({
+			"name": self.name,
+			"id": self.id,
+			"bootNodes": self.boot_nodes,
+			"telemetryEndpoints": if telemetry.is_empty() { None } else { Some(telemetry) },
+			"protocolId": self.protocol_id.unwrap_or_else(|| DEFAULT_PROTOCOL_ID.into()),
+			"consensusEngine": null,
+			"properties": self.properties.unwrap_or_else(chain_spec::token_props),
+			"genesis": { "runtime": genesis },
+		});
+
+		// `ChainSpec::from_genesis` takes a plain `fn`, so the spec goes through its json form.
+		// The command runs once, so leaking the json is fine.
+		let json = serde_json::to_vec(&spec).map_err(|e| format!("Unable to serialize the chain spec: {}", e))?;
+		ChainSpec::from_embedded(Box::leak(json.into_boxed_slice()))
+	}
+}
@@ -9,7 +9,9 @@ extern crate hex_literal;
 mod consts;
 // mod error;
 mod chain_spec;
+mod custom_spec;
 mod service;
+mod rpc;
 mod cli;
 
 pub use substrate_cli::{VersionInfo, IntoExit, error};
@@ -0,0 +1,471 @@
+//! `c2fc_*` JSON-RPC namespace. Proxies calls to the `C2fcApi` runtime API.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::{Future, Stream};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{PubSubHandler, PubSubMetadata, Session, SubscriptionId};
+use jsonrpc_pubsub::typed::{Sink, Subscriber};
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use log::{info, warn};
+
+use akropolis_runtime::{AccountId, Balance, BlockNumber, Hash};
+use akropolis_runtime::{Bucket, FreePromise, Promise, PromiseFilter, C2fcEvent};
+use akropolis_runtime::opaque::Block;
+use akropolis_runtime::c2fc_api::C2fcApi as C2fcRuntimeApi;
+use primitives::Blake2Hasher;
+use runtime_primitives::generic::BlockId;
+use runtime_primitives::traits::{Header as HeaderT, ProvideRuntimeApi};
+use substrate_client::{self as client, Client};
+use substrate_service::TaskExecutor;
+
+/// Number of items in the page of owner listings.
+pub const PAGE_SIZE: u32 = 50;
+
+pub type BucketOf = Bucket<Hash, Balance, AccountId, BlockNumber>;
+pub type PromiseOf = Promise<Hash, Balance, AccountId, BlockNumber>;
+pub type FreePromiseOf = FreePromise<Hash, Balance, BlockNumber>;
+
+/// Page of a listing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+	/// Index of the page.
+	pub page: u32,
+	/// Total number of items in the listing.
+	pub total: u64,
+	/// Items of the page.
+	pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+	fn of(mut all: Vec<T>, page: u32) -> Self {
+		let total = all.len() as u64;
+		let skip = (page as usize).saturating_mul(PAGE_SIZE as usize);
+		let items = if skip < all.len() {
+			all.drain(skip..).take(PAGE_SIZE as usize).collect()
+		} else {
+			Vec::new()
+		};
+		Page { page, total, items }
+	}
+}
+
+/// C2FC RPC methods. Every method accepts an optional block hash, best block is used by default.
+#[rpc]
+pub trait C2fcRpc {
+	/// Returns the bucket with specified id.
+	#[rpc(name = "c2fc_bucket")]
+	fn bucket(&self, id: Hash, at: Option<Hash>) -> Result<Option<BucketOf>>;
+
+	/// Returns the promise with specified id in the state it was created.
+	#[rpc(name = "c2fc_promise")]
+	fn promise(&self, id: Hash, at: Option<Hash>) -> Result<Option<FreePromiseOf>>;
+
+	/// Returns the promise with specified id if it is accepted to a bucket.
+	#[rpc(name = "c2fc_acceptedPromise")]
+	fn accepted_promise(&self, id: Hash, at: Option<Hash>) -> Result<Option<PromiseOf>>;
+
+	/// Ids of buckets owned by the account.
+	#[rpc(name = "c2fc_bucketsOf")]
+	fn buckets_of(&self, who: AccountId, page: Option<u32>, at: Option<Hash>) -> Result<Page<Hash>>;
+
+	/// Ids of promises issued by the account.
+	#[rpc(name = "c2fc_promisesOf")]
+	fn promises_of(&self, who: AccountId, page: Option<u32>, at: Option<Hash>) -> Result<Page<Hash>>;
+
+	/// Amount which should be filled to the promise to avoid any breach up to block `until`.
+	#[rpc(name = "c2fc_dueAmount")]
+	fn due_amount(&self, promise_id: Hash, until: BlockNumber, at: Option<Hash>) -> Result<Balance>;
+
+	/// Ids of promises matching the filter.
+	#[rpc(name = "c2fc_freePromises")]
+	fn free_promises(&self, page: Option<u32>, filter: Option<PromiseFilter<AccountId, Balance>>, at: Option<Hash>) -> Result<Vec<Hash>>;
+
+	/// Decoded `Cashflow` events of the block.
+	#[rpc(name = "c2fc_events")]
+	fn events(&self, at: Option<Hash>) -> Result<Vec<EventNotification>>;
+
+	/// Index and message of every error of `Failed` event.
+	#[rpc(name = "c2fc_errors")]
+	fn errors(&self, at: Option<Hash>) -> Result<Vec<(u8, String)>>;
+}
+
+/// Implementation of the `c2fc_*` RPC methods.
+pub struct C2fc<B, E, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+}
+
+impl<B, E, RA> C2fc<B, E, RA> {
+	pub fn new(client: Arc<Client<B, E, Block, RA>>) -> Self {
+		C2fc { client }
+	}
+}
+
+fn runtime_error<E: std::fmt::Debug>(e: E) -> Error {
+	Error {
+		code: ErrorCode::ServerError(1),
+		message: "Runtime API call failed".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<B, E, RA> C2fc<B, E, RA> where
+	B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	fn block_hash(&self, at: Option<Hash>) -> Result<Hash> {
+		match at {
+			Some(hash) => Ok(hash),
+			None => Ok(self.client.info().map_err(runtime_error)?.chain.best_hash),
+		}
+	}
+
+	fn block_id(&self, at: Option<Hash>) -> Result<BlockId<Block>> {
+		Ok(BlockId::hash(self.block_hash(at)?))
+	}
+}
+
+impl<B, E, RA> C2fcRpc for C2fc<B, E, RA> where
+	B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber>,
+{
+	fn bucket(&self, id: Hash, at: Option<Hash>) -> Result<Option<BucketOf>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().bucket(&at, id).map_err(runtime_error)
+	}
+
+	fn promise(&self, id: Hash, at: Option<Hash>) -> Result<Option<FreePromiseOf>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().promise(&at, id).map_err(runtime_error)
+	}
+
+	fn accepted_promise(&self, id: Hash, at: Option<Hash>) -> Result<Option<PromiseOf>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().accepted_promise(&at, id).map_err(runtime_error)
+	}
+
+	fn buckets_of(&self, who: AccountId, page: Option<u32>, at: Option<Hash>) -> Result<Page<Hash>> {
+		let at = self.block_id(at)?;
+		let all = self.client.runtime_api().buckets_of(&at, who).map_err(runtime_error)?;
+		Ok(Page::of(all, page.unwrap_or(0)))
+	}
+
+	fn promises_of(&self, who: AccountId, page: Option<u32>, at: Option<Hash>) -> Result<Page<Hash>> {
+		let at = self.block_id(at)?;
+		let all = self.client.runtime_api().promises_of(&at, who).map_err(runtime_error)?;
+		Ok(Page::of(all, page.unwrap_or(0)))
+	}
+
+	fn due_amount(&self, promise_id: Hash, until: BlockNumber, at: Option<Hash>) -> Result<Balance> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().due_amount(&at, promise_id, until).map_err(runtime_error)
+	}
+
+	fn free_promises(&self, page: Option<u32>, filter: Option<PromiseFilter<AccountId, Balance>>, at: Option<Hash>) -> Result<Vec<Hash>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api()
+			.free_promises(&at, page.unwrap_or(0), filter.unwrap_or_default())
+			.map_err(runtime_error)
+	}
+
+	fn events(&self, at: Option<Hash>) -> Result<Vec<EventNotification>> {
+		let hash = self.block_hash(at)?;
+		let at = BlockId::hash(hash);
+		let number = self.client.header(&at).map_err(runtime_error)?
+			.map(|header| *header.number())
+			.ok_or_else(|| runtime_error("Unknown block"))?;
+		let events = self.client.runtime_api().events(&at).map_err(runtime_error)?;
+		Ok(events.into_iter().map(|event| EventNotification::new(hash, number, event)).collect())
+	}
+
+	fn errors(&self, at: Option<Hash>) -> Result<Vec<(u8, String)>> {
+		let at = self.block_id(at)?;
+		let errors = self.client.runtime_api().errors(&at).map_err(runtime_error)?;
+		Ok(errors.into_iter().map(|(index, message)| (index, String::from_utf8_lossy(&message).into_owned())).collect())
+	}
+}
+
+/// Origins allowed by the `--rpc-cors` of the node, any origin is allowed if it's not set.
+fn map_cors<T: for<'a> From<&'a str>>(cors: Option<&Vec<String>>) -> jsonrpc_http_server::DomainsValidation<T> {
+	cors.map(|cors| cors.iter().map(AsRef::as_ref).map(Into::into).collect::<Vec<_>>()).into()
+}
+
+/// Starts the HTTP server with the `c2fc_*` namespace.
+pub fn start_http<B, E, RA>(addr: &SocketAddr, cors: Option<&Vec<String>>, client: Arc<Client<B, E, Block, RA>>)
+	-> std::io::Result<jsonrpc_http_server::Server>
+	where C2fc<B, E, RA>: C2fcRpc,
+{
+	let mut io = IoHandler::default();
+	io.extend_with(C2fc::new(client).to_delegate());
+
+	let server = jsonrpc_http_server::ServerBuilder::new(io)
+		.threads(1)
+		.cors(map_cors(cors))
+		.start_http(addr)?;
+
+	info!("C2FC RPC HTTP server listening on {}", addr);
+	Ok(server)
+}
+
+
+// events subscription //
+
+/// RPC metadata of the websocket session.
+#[derive(Default, Clone)]
+pub struct Metadata {
+	session: Option<Arc<Session>>,
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+impl PubSubMetadata for Metadata {
+	fn session(&self) -> Option<Arc<Session>> {
+		self.session.clone()
+	}
+}
+
+/// Filter of the events subscription. All specified fields should match.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilter {
+	/// Events mentioning the account, its buckets or promises.
+	pub account: Option<AccountId>,
+	/// Events of the bucket.
+	pub bucket: Option<Hash>,
+	/// Events of the promise.
+	pub promise: Option<Hash>,
+}
+
+/// Decoded `Cashflow` event.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventNotification {
+	pub block_hash: Hash,
+	pub block_number: BlockNumber,
+	/// Name of the event, e.g. `PromiseBreached`.
+	pub name: String,
+	/// Accounts mentioned in the event.
+	pub accounts: Vec<AccountId>,
+	pub bucket: Option<Hash>,
+	pub promise: Option<Hash>,
+	pub amount: Option<Balance>,
+	/// Index of the error of `Failed` event.
+	pub error: Option<u8>,
+}
+
+impl EventNotification {
+	fn new(block_hash: Hash, block_number: BlockNumber, event: C2fcEvent<AccountId, Hash, Balance, Balance>) -> Self {
+		use C2fcEvent::*;
+
+		let error = match event {
+			Failed(_, error) => Some(error as u8),
+			_ => None,
+		};
+
+		let (name, accounts, bucket, promise, amount) = match event {
+			C2fcCreated(who, c2fc_id) => ("C2fcCreated", vec![who], Some(c2fc_id), None, None),
+			PriceSet(who, c2fc_id, price) => ("PriceSet", vec![who], Some(c2fc_id), None, Some(price)),
+			Transferred(from, to, c2fc_id) => ("Transferred", vec![from, to], Some(c2fc_id), None, None),
+			Bought(buyer, seller, c2fc_id, price) => ("Bought", vec![buyer, seller], Some(c2fc_id), None, Some(price)),
+			PromiseCreated(who, promise_id) => ("PromiseCreated", vec![who], None, Some(promise_id), None),
+			PromiseChanged(promise_id) => ("PromiseChanged", vec![], None, Some(promise_id), None),
+			PromiseAccepted(promise_id, c2fc_id) => ("PromiseAccepted", vec![], Some(c2fc_id), Some(promise_id), None),
+			PromiseFilled(c2fc_id, promise_id, value) => ("PromiseFilled", vec![], Some(c2fc_id), Some(promise_id), Some(value)),
+			PromiseFullilled(c2fc_id, promise_id) => ("PromiseFullilled", vec![], Some(c2fc_id), Some(promise_id), None),
+			PromiseBreached(c2fc_id, promise_id, missed) => ("PromiseBreached", vec![], Some(c2fc_id), Some(promise_id), Some(missed)),
+			Stake(promise_id, who, amount) => ("Stake", vec![who], None, Some(promise_id), Some(amount)),
+			Withdraw(promise_id, who, amount) => ("Withdraw", vec![who], None, Some(promise_id), Some(amount)),
+			EscrowDeposited(promise_id, who, amount) => ("EscrowDeposited", vec![who], None, Some(promise_id), Some(amount)),
+			EscrowWithdrawn(promise_id, who, amount) => ("EscrowWithdrawn", vec![who], None, Some(promise_id), Some(amount)),
+			PromiseAutoPaid(c2fc_id, promise_id, who, value) => ("PromiseAutoPaid", vec![who], Some(c2fc_id), Some(promise_id), Some(value)),
+			StreamClaimed(c2fc_id, promise_id, who, value) => ("StreamClaimed", vec![who], Some(c2fc_id), Some(promise_id), Some(value)),
+			ParametersChanged => ("ParametersChanged", vec![], None, None, None),
+			C2fcClosed(who, c2fc_id) => ("C2fcClosed", vec![who], Some(c2fc_id), None, None),
+			PromiseClosed(who, promise_id) => ("PromiseClosed", vec![who], None, Some(promise_id), None),
+			Failed(who, _) => ("Failed", vec![who], None, None, None),
+			FeePaid(c2fc_id, who, fee) => ("FeePaid", vec![who], Some(c2fc_id), None, Some(fee)),
+			RoyaltyPaid(c2fc_id, creator, royalty) => ("RoyaltyPaid", vec![creator], Some(c2fc_id), None, Some(royalty)),
+			Approval(owner, approved, c2fc_id) => ("Approval", vec![owner, approved], Some(c2fc_id), None, None),
+			ApprovalForAll(owner, operator, _) => ("ApprovalForAll", vec![owner, operator], None, None, None),
+			AssetTreasurySet(_, treasury) => ("AssetTreasurySet", vec![treasury], None, None, None),
+			LegacyStakeUnlocked(promise_id, who, amount) => ("LegacyStakeUnlocked", vec![who], None, Some(promise_id), Some(amount)),
+		};
+
+		EventNotification { block_hash, block_number, name: name.into(), accounts, bucket, promise, amount, error }
+	}
+}
+
+/// Buckets and promises of the account filtered by.
+type Portfolio = (Vec<Hash>, Vec<Hash>);
+
+impl EventFilter {
+	fn matches(&self, event: &EventNotification, portfolio: Option<&Portfolio>) -> bool {
+		let account = match (&self.account, portfolio) {
+			(Some(who), Some((buckets, promises))) =>
+				event.accounts.contains(who)
+					|| event.bucket.map_or(false, |id| buckets.contains(&id))
+					|| event.promise.map_or(false, |id| promises.contains(&id)),
+			(Some(who), None) => event.accounts.contains(who),
+			(None, _) => true,
+		};
+		account
+			&& self.bucket.map_or(true, |id| event.bucket == Some(id))
+			&& self.promise.map_or(true, |id| event.promise == Some(id))
+	}
+}
+
+/// C2FC events subscription.
+#[rpc]
+pub trait C2fcEventsRpc {
+	type Metadata;
+
+	/// Subscribe to decoded `Cashflow` events of the new best blocks.
+	#[pubsub(subscription = "c2fc_events", subscribe, name = "c2fc_subscribeEvents")]
+	fn subscribe_events(&self, metadata: Self::Metadata, subscriber: Subscriber<EventNotification>, filter: Option<EventFilter>);
+
+	/// Unsubscribe from the events.
+	#[pubsub(subscription = "c2fc_events", unsubscribe, name = "c2fc_unsubscribeEvents")]
+	fn unsubscribe_events(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
+
+type Subscriptions = Arc<Mutex<HashMap<SubscriptionId, (Sink<EventNotification>, EventFilter)>>>;
+
+/// Implementation of the events subscription.
+pub struct C2fcEvents {
+	subscriptions: Subscriptions,
+	next_id: AtomicUsize,
+}
+
+impl C2fcEvents {
+	/// Creates the subscription handler and starts listening to imported blocks.
+	pub fn new<B, E, RA>(client: Arc<Client<B, E, Block, RA>>, executor: TaskExecutor) -> Self where
+		B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+		E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+		RA: Send + Sync + 'static,
+		Client<B, E, Block, RA>: ProvideRuntimeApi,
+		<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber>,
+	{
+		let subscriptions = Subscriptions::default();
+
+		let publisher = {
+			let subscriptions = subscriptions.clone();
+			let executor = executor.clone();
+			client.import_notification_stream()
+				.filter(|notification| notification.is_new_best)
+				.for_each(move |notification| {
+					publish(&*client, &subscriptions, &executor, notification.hash, *notification.header.number());
+					Ok(())
+				})
+		};
+		executor.spawn(publisher);
+
+		C2fcEvents { subscriptions, next_id: AtomicUsize::new(0) }
+	}
+}
+
+/// Sends the events of the block to the matching subscribers.
+fn publish<B, E, RA>(client: &Client<B, E, Block, RA>, subscriptions: &Subscriptions, executor: &TaskExecutor, hash: Hash, number: BlockNumber) where
+	B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber>,
+{
+	// the runtime API is called on the snapshot, not under the lock:
+	let subscribers: Vec<_> = subscriptions.lock().iter()
+		.map(|(id, (sink, filter))| (id.clone(), sink.clone(), filter.clone()))
+		.collect();
+	if subscribers.is_empty() {
+		return;
+	}
+
+	let at = BlockId::hash(hash);
+	let api = client.runtime_api();
+	let events: Vec<_> = match api.events(&at) {
+		Ok(events) => events.into_iter().map(|event| EventNotification::new(hash, number, event)).collect(),
+		Err(e) => {
+			warn!("Unable to read C2FC events of block {}: {:?}", hash, e);
+			return;
+		},
+	};
+	if events.is_empty() {
+		return;
+	}
+
+	// portfolios are read once per account:
+	let mut portfolios = HashMap::new();
+	for (id, sink, filter) in subscribers {
+		let portfolio = filter.account.as_ref().map(|who| portfolios.entry(who.clone())
+			.or_insert_with(|| (
+				api.buckets_of(&at, who.clone()).unwrap_or_default(),
+				api.promises_of(&at, who.clone()).unwrap_or_default(),
+			))
+			.clone());
+
+		for event in events.iter().filter(|event| filter.matches(event, portfolio.as_ref())) {
+			// the sink fails when the session is gone, the subscription is dropped then:
+			let subscriptions = subscriptions.clone();
+			let id = id.clone();
+			executor.spawn(sink.notify(Ok(event.clone())).map(|_| ()).map_err(move |_| {
+				subscriptions.lock().remove(&id);
+			}));
+		}
+	}
+}
+
+impl C2fcEventsRpc for C2fcEvents {
+	type Metadata = Metadata;
+
+	fn subscribe_events(&self, metadata: Self::Metadata, subscriber: Subscriber<EventNotification>, filter: Option<EventFilter>) {
+		let id = SubscriptionId::Number(self.next_id.fetch_add(1, Ordering::SeqCst) as u64);
+		if let Ok(sink) = subscriber.assign_id(id.clone()) {
+			self.subscriptions.lock().insert(id.clone(), (sink, filter.unwrap_or_default()));
+
+			// the subscription is dropped with the websocket session:
+			if let Some(session) = metadata.session() {
+				let subscriptions = self.subscriptions.clone();
+				session.on_drop(move || {
+					subscriptions.lock().remove(&id);
+				});
+			}
+		}
+	}
+
+	fn unsubscribe_events(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+		Ok(self.subscriptions.lock().remove(&id).is_some())
+	}
+}
+
+/// Starts the websocket server with the `c2fc_*` events subscription.
+pub fn start_ws<B, E, RA>(addr: &SocketAddr, cors: Option<&Vec<String>>, client: Arc<Client<B, E, Block, RA>>, executor: TaskExecutor)
+	-> std::io::Result<jsonrpc_ws_server::Server>
+	where
+		B: client::backend::Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+		E: client::CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+		RA: Send + Sync + 'static,
+		Client<B, E, Block, RA>: ProvideRuntimeApi,
+		<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block, AccountId, Hash, Balance, BlockNumber>,
+{
+	let mut io = PubSubHandler::<Metadata>::default();
+	io.extend_with(C2fc::new(client.clone()).to_delegate());
+	io.extend_with(C2fcEvents::new(client, executor).to_delegate());
+
+	let server = jsonrpc_ws_server::ServerBuilder::with_meta_extractor(io, |context: &jsonrpc_ws_server::RequestContext| Metadata {
+			session: Some(Arc::new(Session::new(context.sender()))),
+		})
+		.allowed_origins(map_cors(cors))
+		.start(addr)
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+
+	info!("C2FC RPC WS server listening on {}", addr);
+	Ok(server)
+}